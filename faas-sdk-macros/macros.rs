@@ -0,0 +1,63 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, FnArg, ItemFn, ReturnType};
+
+/// Exports `fn(Input) -> Result<Output, E>` as the `exec` function of the component,
+/// see `faas::handler` for the details
+#[proc_macro_attribute]
+pub fn handler(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(item as ItemFn);
+    let expanded = match check(attr.into(), &function) {
+        Ok(()) => export(&function),
+        Err(err) => err.to_compile_error(),
+    };
+    expanded.into()
+}
+
+fn check(attr: TokenStream2, function: &ItemFn) -> syn::Result<()> {
+    let signature = &function.sig;
+    if !attr.is_empty() {
+        return Err(syn::Error::new_spanned(attr, "handler takes no arguments"));
+    }
+    if let Some(asyncness) = signature.asyncness {
+        return Err(syn::Error::new_spanned(
+            asyncness,
+            "handler can't be async, block on a runtime inside the function",
+        ));
+    }
+    if !signature.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&signature.generics, "handler can't be generic"));
+    }
+    if signature.inputs.len() != 1 || matches!(signature.inputs.first(), Some(FnArg::Receiver(_))) {
+        return Err(syn::Error::new_spanned(
+            &signature.inputs,
+            "handler takes exactly one deserializable input",
+        ));
+    }
+    if matches!(signature.output, ReturnType::Default) {
+        return Err(syn::Error::new_spanned(
+            signature,
+            "handler returns Result<Output, E> with a serializable Output",
+        ));
+    }
+    Ok(())
+}
+
+fn export(function: &ItemFn) -> TokenStream2 {
+    let name = &function.sig.ident;
+    quote! {
+        #function
+
+        #[doc(hidden)]
+        struct __FaasHandler;
+
+        impl ::faas::bindings::Guest for __FaasHandler {
+            fn exec(input: ::std::string::String) -> ::std::string::String {
+                ::faas::__private::run(&input, #name)
+            }
+        }
+
+        ::faas::bindings::export!(__FaasHandler with_types_in ::faas::bindings);
+    }
+}
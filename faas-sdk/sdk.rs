@@ -0,0 +1,137 @@
+//! Guest side of the platform, a function is a plain Rust function with serde types:
+//!
+//! ```ignore
+//! #[faas::handler]
+//! fn greet(input: Input) -> Result<Output, String> {
+//!     Ok(Output { result: format!("Hello {}", input.name) })
+//! }
+//! ```
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+/// Exports `fn(Input) -> Result<Output, E>` as `exec` of the `faas-exec` world.
+///
+/// `Input` is deserialized from the JSON input, `Output` is serialized as JSON output.
+/// Invalid input and errors of the handler fail the execution through `local:faas/outcome`,
+/// the caller gets a `422` with the message, which is logged through `local:faas/logging` as well.
+pub use faas_sdk_macros::handler;
+
+/// Generated bindings of `wit/faas.wit`, for direct use of the host interfaces
+pub mod bindings {
+    wit_bindgen::generate!({
+        world: "faas-exec",
+        path: "../wit",
+        generate_all,
+        pub_export_macro: true,
+        default_bindings_module: "faas::bindings",
+    });
+}
+
+/// Error of a call to another function
+#[derive(Debug, Clone)]
+pub struct CallError {
+    /// HTTP status `POST /exec` would have answered with
+    pub status: u16,
+    pub message: String,
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.status)
+    }
+}
+
+impl std::error::Error for CallError {}
+
+/// Calls the deployed function `{bucket}/{key}` (or `{bucket}/{key}@{alias}`) through `local:faas/invoke`
+pub fn call<I, O>(name: &str, input: &I) -> Result<O, CallError>
+where
+    I: Serialize,
+    O: DeserializeOwned,
+{
+    let input = serde_json::to_string(input).map_err(|e| CallError {
+        status: 400,
+        message: format!("Invalid input of {}: {}", name, e),
+    })?;
+    let output = bindings::local::faas::invoke::call(name, &input).map_err(|e| CallError {
+        status: e.status,
+        message: e.message,
+    })?;
+    serde_json::from_str(&output).map_err(|e| CallError {
+        status: 500,
+        message: format!("Invalid output of {}: {}", name, e),
+    })
+}
+
+/// Config entry or secret of the function, set with `PUT /config/{bucket}/{key}`
+pub fn config(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Structured records through `local:faas/logging`, fields are key-value pairs
+pub mod log {
+    pub use crate::bindings::local::faas::logging::Level;
+
+    pub fn log(level: Level, message: &str, fields: &[(&str, &str)]) {
+        let fields: Vec<(String, String)> = fields
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        crate::bindings::local::faas::logging::log(level, message, &fields);
+    }
+
+    pub fn trace(message: &str, fields: &[(&str, &str)]) {
+        log(Level::Trace, message, fields)
+    }
+
+    pub fn debug(message: &str, fields: &[(&str, &str)]) {
+        log(Level::Debug, message, fields)
+    }
+
+    pub fn info(message: &str, fields: &[(&str, &str)]) {
+        log(Level::Info, message, fields)
+    }
+
+    pub fn warn(message: &str, fields: &[(&str, &str)]) {
+        log(Level::Warn, message, fields)
+    }
+
+    pub fn error(message: &str, fields: &[(&str, &str)]) {
+        log(Level::Error, message, fields)
+    }
+}
+
+/// Used by the code generated by `handler`
+#[doc(hidden)]
+pub mod __private {
+    use serde::Serialize;
+    use serde::de::DeserializeOwned;
+    use std::fmt::Display;
+
+    pub fn run<I, O, E, F>(input: &str, handler: F) -> String
+    where
+        I: DeserializeOwned,
+        O: Serialize,
+        E: Display,
+        F: FnOnce(I) -> Result<O, E>,
+    {
+        let input = match serde_json::from_str(input) {
+            Ok(input) => input,
+            Err(e) => return failure(format!("Invalid input: {}", e)),
+        };
+        match handler(input) {
+            Ok(output) => serde_json::to_string(&output)
+                .unwrap_or_else(|e| failure(format!("Invalid output: {}", e))),
+            Err(e) => failure(e.to_string()),
+        }
+    }
+
+    fn failure(message: String) -> String {
+        crate::log::error(&message, &[]);
+        crate::bindings::local::faas::outcome::fail(&message);
+        // Discarded by the host
+        String::new()
+    }
+}
@@ -1,30 +1,3 @@
-wit_bindgen::generate!({
-    world: "faas-exec",
-    path: "../wit",
-    generate_all,
-});
-
-struct GuestImpl;
-
-impl Guest for GuestImpl {
-    fn exec(input: String) -> String {
-        let input: Input = serde_json::from_str(&input).unwrap();
-
-        let mut a = 1;
-        let mut b = 1;
-        for _ in 0..input.n {
-            let t = a;
-            a = b;
-            b += t;
-        }
-
-        let output = Output { result: b };
-        serde_json::to_string(&output).unwrap()
-    }
-}
-
-export!(GuestImpl);
-
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize)]
@@ -35,4 +8,19 @@ struct Input {
 #[derive(Serialize)]
 struct Output {
     result: u32,
-}
\ No newline at end of file
+}
+
+#[faas::handler]
+fn fibonacci(input: Input) -> Result<Output, String> {
+    let mut a: u32 = 1;
+    let mut b: u32 = 1;
+    for _ in 0..input.n {
+        let t = a;
+        a = b;
+        b = b
+            .checked_add(t)
+            .ok_or_else(|| format!("Fibonacci number {} does not fit into u32", input.n))?;
+    }
+
+    Ok(Output { result: b })
+}
@@ -1,24 +1,5 @@
-wit_bindgen::generate!({
-    world: "faas-exec",
-    path: "../wit",
-    generate_all,
-});
-
-struct GuestImpl;
-
-impl Guest for GuestImpl {
-    fn exec(input: String) -> String {
-        let input: Input = serde_json::from_str(&input).unwrap();
-        let greetings = format!("Hello {}, how are you?", input.name);
-        let output = Output { result: greetings };
-        println!("--- Test output ---");
-        serde_json::to_string(&output).unwrap()
-    }
-}
-
-export!(GuestImpl);
-
 use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
 
 #[derive(Deserialize)]
 struct Input {
@@ -28,4 +9,12 @@ struct Input {
 #[derive(Serialize)]
 struct Output {
     result: String,
-}
\ No newline at end of file
+}
+
+#[faas::handler]
+fn hello(input: Input) -> Result<Output, Infallible> {
+    faas::log::info("Greeting", &[("name", &input.name)]);
+    Ok(Output {
+        result: format!("Hello {}, how are you?", input.name),
+    })
+}
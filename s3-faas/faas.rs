@@ -1,42 +1,5 @@
-wit_bindgen::generate!({
-    world: "faas-exec",
-    path: "../wit",
-    generate_all,
-});
-
-struct GuestImpl;
-
-impl Guest for GuestImpl {
-    fn exec(input: String) -> String {
-        let input: Input = serde_json::from_str(&input).unwrap();
-
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_time()
-            .build()
-            .expect("Failed to generate runtime");
-
-        let output = rt.block_on(async {
-            match list_objects(&input.bucket).await {
-                Ok(files) => Output {
-                    success: true,
-                    files,
-                },
-                Err(_) => Output {
-                    success: false,
-                    files: vec![],
-                },
-            }
-        });
-
-        serde_json::to_string(&output).unwrap()
-    }
-}
-
-export!(GuestImpl);
-
 use serde::{Deserialize, Serialize};
 use aws_config::Region;
-use aws_sdk_s3 as s3;
 use aws_sdk_s3::Client;
 use aws_sdk_s3::config::Credentials;
 use aws_smithy_async::rt::sleep::TokioSleep;
@@ -53,6 +16,20 @@ struct Output {
     files: Vec<File>,
 }
 
+#[faas::handler]
+fn list(input: Input) -> Result<Output, String> {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .map_err(|e| format!("Failed to create runtime: {}", e))?;
+
+    let files = rt.block_on(list_objects(&input.bucket))?;
+    Ok(Output {
+        success: true,
+        files,
+    })
+}
+
 #[derive(Serialize)]
 struct File {
     name: String,
@@ -65,20 +42,23 @@ async fn list_objects(bucket: &str) -> Result<Vec<File>, String> {
 
     let sleep = TokioSleep::new();
 
+    // Set per function with PUT /config/{bucket}/{key}, the keys as secrets
     let credentials = Credentials::new(
-        "rustfsadmin".to_string(),
-        "rustfsadmin".to_string(),
+        env("S3_ACCESS_KEY")?,
+        env("S3_SECRET_KEY")?,
         None,
         None,
-        "rustfs",
+        "faas-config",
     );
+    let region = env("S3_REGION").unwrap_or_else(|_| "eu-west-1".to_string());
+    let endpoint = env("S3_ENDPOINT").unwrap_or_else(|_| "http://localhost:9000".to_string());
 
     let config = aws_config::from_env()
-        .region(Region::new("eu-west-1"))
+        .region(Region::new(region))
         .credentials_provider(credentials)
         .http_client(http_client)
         .sleep_impl(sleep)
-        .endpoint_url("http://localhost:9000".to_string())
+        .endpoint_url(endpoint)
         .load()
         .await;
 
@@ -106,4 +86,8 @@ async fn list_objects(bucket: &str) -> Result<Vec<File>, String> {
         .collect();
 
     Ok(files)
-}
\ No newline at end of file
+}
+
+fn env(name: &str) -> Result<String, String> {
+    faas::config(name).ok_or_else(|| format!("{} is not configured", name))
+}
@@ -0,0 +1,116 @@
+use crate::error::AppError;
+use crate::tools::alias::{self, Alias, Aliases, Canary};
+use crate::tools::names;
+use crate::tools::s3::{GetOptions, S3};
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct AliasRequest {
+    pub version: String,
+    pub canary: Option<Canary>,
+}
+
+pub async fn list_aliases(
+    State(s3): State<Arc<S3>>,
+    Path((bucket, key)): Path<(String, String)>,
+) -> Result<Json<Aliases>, AppError> {
+    names::validate_object(&bucket, &key)?;
+    Ok(Json(alias::load(&s3, &bucket, &key).await?))
+}
+
+pub async fn put_alias(
+    State(s3): State<Arc<S3>>,
+    Path((bucket, path)): Path<(String, String)>,
+    Json(request): Json<AliasRequest>,
+) -> Result<Json<Alias>, AppError> {
+    let (key, name) = alias_path(&bucket, &path)?;
+    if request.canary.as_ref().is_some_and(|c| c.weight > 100) {
+        return Err(AppError::BadRequest("Canary weight must be between 0 and 100".to_string()));
+    }
+
+    check_version(&s3, &bucket, key, &request.version).await?;
+    if let Some(canary) = &request.canary {
+        check_version(&s3, &bucket, key, &canary.version).await?;
+    }
+
+    let mut aliases = alias::load(&s3, &bucket, key).await?;
+    let previous = aliases
+        .get(name)
+        .map(|a| a.version.clone())
+        .filter(|v| *v != request.version);
+
+    let entry = Alias {
+        version: request.version,
+        canary: request.canary,
+        previous: previous.or_else(|| aliases.get(name).and_then(|a| a.previous.clone())),
+    };
+    aliases.insert(name.to_string(), entry.clone());
+    alias::save(&s3, &bucket, key, &aliases).await?;
+
+    Ok(Json(entry))
+}
+
+pub async fn delete_alias(
+    State(s3): State<Arc<S3>>,
+    Path((bucket, path)): Path<(String, String)>,
+) -> Result<Json<String>, AppError> {
+    let (key, name) = alias_path(&bucket, &path)?;
+    let mut aliases = alias::load(&s3, &bucket, key).await?;
+    if aliases.remove(name).is_none() {
+        return Err(AppError::NotFound(format!("Alias {} not found for {}", name, key)));
+    }
+    alias::save(&s3, &bucket, key, &aliases).await?;
+    Ok(Json(format!("Alias {} deleted from {}", name, key)))
+}
+
+/// Drops the canary and points the alias back to its previous version
+pub async fn rollback_alias(
+    State(s3): State<Arc<S3>>,
+    Path((bucket, path)): Path<(String, String)>,
+) -> Result<Json<Alias>, AppError> {
+    let path = path
+        .strip_suffix("/rollback")
+        .ok_or_else(|| AppError::NotFound(format!("No POST route for {}", path)))?;
+    let (key, name) = alias_path(&bucket, path)?;
+    let mut aliases = alias::load(&s3, &bucket, key).await?;
+    let entry = aliases
+        .get_mut(name)
+        .ok_or_else(|| AppError::NotFound(format!("Alias {} not found for {}", name, key)))?;
+
+    if entry.canary.is_some() {
+        entry.canary = None;
+    } else if let Some(previous) = entry.previous.take() {
+        entry.version = previous;
+    } else {
+        return Err(AppError::BadRequest(format!("Alias {} has nothing to roll back", name)));
+    }
+
+    let entry = entry.clone();
+    alias::save(&s3, &bucket, key, &aliases).await?;
+    Ok(Json(entry))
+}
+
+/// A mistyped version would only fail once the alias is executed
+async fn check_version(s3: &S3, bucket: &str, key: &str, version: &str) -> Result<(), AppError> {
+    let options = GetOptions {
+        version: Some(version),
+        ..GetOptions::default()
+    };
+    match s3.head_object(bucket, key, &options).await {
+        Err(AppError::NotFound(_) | AppError::BadRequest(_)) => Err(AppError::BadRequest(format!(
+            "Version {} of {} not found",
+            version, key
+        ))),
+        result => result.map(|_| ()),
+    }
+}
+
+/// `{*path}` ends with the alias name, everything before it is the key
+fn alias_path<'a>(bucket: &str, path: &'a str) -> Result<(&'a str, &'a str), AppError> {
+    let (key, name) = names::split_last(path)?;
+    names::validate_object(bucket, key)?;
+    Ok((key, name))
+}
@@ -1,35 +1,229 @@
 use crate::error::AppError;
-use crate::tools::s3::S3;
-use axum::extract::{Path, State};
+use crate::tools::cache::ModuleCache;
+use crate::tools::jobs::system_bucket;
+use crate::tools::names;
+use crate::tools::registry;
+use crate::tools::s3::{BucketEntry, ObjectRef, S3, INTERNAL_PREFIX};
+use axum::extract::{Path, Query, State};
 use axum::Json;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+/// Bucket tag blocking the deletion of a bucket
+const PROTECTED_TAG: &str = "faas-protected";
+
+const DEFAULT_LIMIT: i32 = 100;
+const MAX_LIMIT: i32 = 1000;
+
+#[derive(Deserialize)]
+pub struct BucketListQuery {
+    pub prefix: Option<String>,
+    pub delimiter: Option<String>,
+    pub limit: Option<i32>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct FileEntry {
+    pub key: String,
+    pub size: i64,
+    pub last_modified: Option<i64>,
+    pub etag: Option<String>,
+    pub versions: usize,
+}
+
+/// Internal objects below `.faas/` are left out, a page may hold less than `limit` entries
 #[derive(Serialize)]
 pub struct BucketListResponse {
-    pub files: Vec<String>,
+    pub files: Vec<FileEntry>,
+    pub prefixes: Vec<String>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BucketDeleteQuery {
+    #[serde(default)]
+    pub recursive: bool,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct BucketDeleteResponse {
+    pub message: String,
+    pub dry_run: bool,
+    /// Object versions and delete markers removed with the bucket
+    pub objects: Vec<ObjectRef>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Protection {
+    pub protected: bool,
+}
+
+#[derive(Serialize)]
+pub struct BucketsResponse {
+    pub buckets: Vec<BucketEntry>,
+}
+
+pub async fn list_buckets(State(s3): State<Arc<S3>>) -> Result<Json<BucketsResponse>, AppError> {
+    let system = system_bucket();
+    Ok(Json(BucketsResponse {
+        buckets: s3
+            .list_buckets()
+            .await?
+            .into_iter()
+            .filter(|b| b.name != system)
+            .collect(),
+    }))
 }
 
 pub async fn create_bucket(
     State(s3): State<Arc<S3>>,
     Path(bucket): Path<String>,
 ) -> Result<Json<String>, AppError> {
+    names::validate_bucket(&bucket)?;
     s3.create_bucket(&bucket).await?;
     Ok(Json(format!("Bucket {} created", bucket)))
 }
 
 pub async fn delete_bucket(
     State(s3): State<Arc<S3>>,
+    State(cache): State<Arc<ModuleCache>>,
     Path(bucket): Path<String>,
-) -> Result<Json<String>, AppError> {
+    Query(query): Query<BucketDeleteQuery>,
+) -> Result<Json<BucketDeleteResponse>, AppError> {
+    names::validate_bucket(&bucket)?;
+    if is_protected(&s3, &bucket).await? {
+        return Err(AppError::Forbidden(format!(
+            "Bucket {} is protected, remove the protection first",
+            bucket
+        )));
+    }
+
+    let objects = s3.list_all_versions(&bucket).await?;
+    if !objects.is_empty() && !query.recursive {
+        return Err(AppError::Conflict(format!(
+            "Bucket {} contains {} objects, use recursive=true to delete them",
+            bucket,
+            objects.len()
+        )));
+    }
+
+    if query.dry_run {
+        return Ok(Json(BucketDeleteResponse {
+            message: format!("Bucket {} would be deleted with {} objects", bucket, objects.len()),
+            dry_run: true,
+            objects,
+        }));
+    }
+
+    // Digests are only known from the registry, collect them before it is deleted
+    let sha256s: Vec<String> = registry::list(&s3, &bucket)
+        .await?
+        .into_iter()
+        .flat_map(|meta| meta.versions.into_iter().map(|v| v.sha256))
+        .collect();
+
+    s3.delete_objects(&bucket, &objects).await?;
     s3.delete_bucket(&bucket).await?;
-    Ok(Json(format!("Bucket {} deleted", bucket)))
+    cache.evict(&sha256s).await;
+
+    Ok(Json(BucketDeleteResponse {
+        message: format!("Bucket {} deleted", bucket),
+        dry_run: false,
+        objects,
+    }))
+}
+
+async fn is_protected(s3: &S3, bucket: &str) -> Result<bool, AppError> {
+    Ok(s3
+        .get_tags(bucket)
+        .await?
+        .iter()
+        .any(|(key, value)| key == PROTECTED_TAG && value == "true"))
+}
+
+pub async fn get_protection(
+    State(s3): State<Arc<S3>>,
+    Path(bucket): Path<String>,
+) -> Result<Json<Protection>, AppError> {
+    names::validate_bucket(&bucket)?;
+    Ok(Json(Protection {
+        protected: is_protected(&s3, &bucket).await?,
+    }))
+}
+
+pub async fn put_protection(
+    State(s3): State<Arc<S3>>,
+    Path(bucket): Path<String>,
+    Json(protection): Json<Protection>,
+) -> Result<Json<Protection>, AppError> {
+    names::validate_bucket(&bucket)?;
+    let mut tags: Vec<(String, String)> = s3
+        .get_tags(&bucket)
+        .await?
+        .into_iter()
+        .filter(|(key, _)| key != PROTECTED_TAG)
+        .collect();
+    if protection.protected {
+        tags.push((PROTECTED_TAG.to_string(), "true".to_string()));
+    }
+
+    s3.put_tags(&bucket, tags).await?;
+    Ok(Json(protection))
 }
 
 pub async fn list_bucket(
     State(s3): State<Arc<S3>>,
     Path(bucket): Path<String>,
+    Query(query): Query<BucketListQuery>,
 ) -> Result<Json<BucketListResponse>, AppError> {
-    let files = s3.list_files(&bucket).await?;
-    Ok(Json(BucketListResponse { files }))
-}
\ No newline at end of file
+    names::validate_bucket(&bucket)?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(AppError::BadRequest(format!("Limit must be between 1 and {}", MAX_LIMIT)));
+    }
+
+    let page = s3
+        .list_page(
+            &bucket,
+            query.prefix.as_deref(),
+            query.delimiter.as_deref(),
+            limit,
+            query.cursor.as_deref(),
+        )
+        .await?;
+
+    let objects: Vec<_> = page
+        .objects
+        .into_iter()
+        .filter(|o| !o.key.starts_with(INTERNAL_PREFIX))
+        .collect();
+
+    let keys: Vec<String> = objects.iter().map(|o| o.key.clone()).collect();
+    let versions = s3
+        .count_versions(&bucket, query.delimiter.as_deref(), &keys)
+        .await?;
+
+    let files = objects
+        .into_iter()
+        .map(|o| FileEntry {
+            versions: versions.get(&o.key).copied().unwrap_or_default(),
+            key: o.key,
+            size: o.size,
+            last_modified: o.last_modified,
+            etag: o.etag,
+        })
+        .collect();
+
+    Ok(Json(BucketListResponse {
+        files,
+        prefixes: page
+            .prefixes
+            .into_iter()
+            .filter(|p| !p.starts_with(INTERNAL_PREFIX))
+            .collect(),
+        next_cursor: page.next_cursor,
+    }))
+}
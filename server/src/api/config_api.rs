@@ -0,0 +1,48 @@
+use crate::error::AppError;
+use crate::tools::config::{self, ConfigView};
+use crate::tools::names;
+use crate::tools::s3::S3;
+use crate::tools::secrets::Secrets;
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Entries to set, `null` removes an entry, entries that are not mentioned are kept
+#[derive(Deserialize)]
+pub struct ConfigUpdate {
+    #[serde(default)]
+    pub config: BTreeMap<String, Option<String>>,
+    #[serde(default)]
+    pub secrets: BTreeMap<String, Option<String>>,
+}
+
+/// Config of a function, secret values are shown as `***`
+pub async fn get_config(
+    State(s3): State<Arc<S3>>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> Result<Json<ConfigView>, AppError> {
+    names::validate_object(&namespace, &name)?;
+    Ok(Json(config::load(&s3, &namespace, &name).await?.redacted()))
+}
+
+pub async fn put_config(
+    State(s3): State<Arc<S3>>,
+    State(secrets): State<Arc<Secrets>>,
+    Path((namespace, name)): Path<(String, String)>,
+    Json(update): Json<ConfigUpdate>,
+) -> Result<Json<ConfigView>, AppError> {
+    names::validate_object(&namespace, &name)?;
+    let stored = config::update(&s3, &secrets, &namespace, &name, update.config, update.secrets).await?;
+    Ok(Json(stored.redacted()))
+}
+
+pub async fn delete_config(
+    State(s3): State<Arc<S3>>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> Result<Json<String>, AppError> {
+    names::validate_object(&namespace, &name)?;
+    config::remove(&s3, &namespace, &name).await?;
+    Ok(Json(format!("Config of {} deleted from {}", name, namespace)))
+}
@@ -1,97 +1,64 @@
 use crate::error::AppError;
-use crate::tools::s3::S3;
-use axum::extract::{Path, State};
+use crate::tools::invoke::Invoker;
+use crate::tools::jobs::{Job, Jobs};
+use crate::tools::secrets::Secrets;
+use crate::tools::webhook::Callback;
+use axum::extract::{Path, Query, State};
+use axum::http::header::LOCATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
+use serde::Deserialize;
 use serde_json;
 use std::sync::Arc;
 use std::time::Instant;
-use wasmtime::component::{Component, Linker, ResourceTable};
-use wasmtime::{Config, Engine, OptLevel, Store};
-use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiView, WasiCtxView};
-use wasmtime_wasi_http::{WasiHttpCtx, WasiHttpView};
 
-struct ServerState {
-    ctx: WasiCtx,
-    table: ResourceTable,
-    http: WasiHttpCtx,
+#[derive(Deserialize)]
+pub struct ExecQuery {
+    pub version: Option<String>,
+    /// Queue the execution and answer with `202 Accepted` and a job id
+    #[serde(default, rename = "async")]
+    pub run_async: bool,
+    /// Seconds the result of an asynchronous execution is kept
+    pub retention: Option<u64>,
 }
 
-impl WasiView for ServerState {
-    fn ctx(&mut self) -> WasiCtxView<'_> {
-        WasiCtxView {
-            ctx: &mut self.ctx,
-            table: &mut self.table,
-        }
-    }
-}
-
-impl WasiHttpView for ServerState {
-    fn ctx(&mut self) -> &mut WasiHttpCtx {
-        &mut self.http
-    }
-
-    fn table(&mut self) -> &mut ResourceTable {
-        &mut self.table
-    }
-}
-
-wasmtime::component::bindgen!({
-    world: "faas-exec",
-    path: "../wit",
-    exports: {
-        "exec": async
-    }
-});
-
 pub async fn exec_wasm(
-    State(s3): State<Arc<S3>>,
+    State(invoker): State<Arc<Invoker>>,
+    State(jobs): State<Arc<Jobs>>,
+    State(secrets): State<Arc<Secrets>>,
     Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<ExecQuery>,
+    headers: HeaderMap,
     Json(payload): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, AppError> {
-    let start = Instant::now();
-
-    let mut config = Config::new();
-    config.wasm_component_model(true);
-    config.async_support(true);
-    config.debug_info(true);
-    config.cranelift_opt_level(OptLevel::None);
-
-    let engine = Engine::new(&config)?;
-
-    let wasm_stream = s3.download_file(&bucket, &key).await?;
-
-    let wasm_bytes = wasm_stream
-        .collect()
-        .await
-        .map_err(|e| AppError::Internal(e.to_string()))?
-        .to_vec();
-
-    let component = Component::from_binary(&engine, &wasm_bytes)?;
-
-    let mut linker = Linker::new(&engine);
-    wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
-    wasmtime_wasi_http::add_only_http_to_linker_async(&mut linker)?;
-
-    let mut store = Store::new(
-        &engine,
-        ServerState {
-            ctx: WasiCtxBuilder::new().inherit_stdout().build(),
-            table: ResourceTable::new(),
-            http: WasiHttpCtx::new(),
-        },
-    );
+) -> Result<Response, AppError> {
+    // A callback makes the execution fire-and-forget
+    let callback = Callback::from_headers(&headers, &secrets)?;
+    if query.run_async || callback.is_some() {
+        let job = jobs
+            .submit(&bucket, &key, query.version, payload, query.retention, callback)
+            .await?;
+        return Ok(accepted(job));
+    }
 
-    let bindings = FaasExec::instantiate_async(&mut store, &component, &linker).await?;
+    let start = Instant::now();
 
-    let input_json =
-        serde_json::to_string(&payload).map_err(|e| AppError::Internal(e.to_string()))?;
+    let invocation = invoker.invoke(&bucket, &key, query.version, &payload).await?;
 
-    let output_json_str = bindings.call_exec(&mut store, &input_json).await?;
+    println!("Time {}ms", start.elapsed().as_millis());
 
-    let output_json: serde_json::Value =
-        serde_json::from_str(&output_json_str).map_err(|e| AppError::Internal(e.to_string()))?;
+    let mut headers = HeaderMap::new();
+    if let Some(version) = invocation.version {
+        headers.insert(
+            "x-faas-version",
+            version.parse().map_err(|_| AppError::Internal("Invalid version id".to_string()))?,
+        );
+    }
 
-    println!("Time {}ms", start.elapsed().as_millis());
+    Ok((headers, Json(invocation.output)).into_response())
+}
 
-    Ok(Json(output_json))
-}
\ No newline at end of file
+fn accepted(job: Job) -> Response {
+    let location = format!("/jobs/{}", job.id);
+    (StatusCode::ACCEPTED, [(LOCATION, location)], Json(job.redacted())).into_response()
+}
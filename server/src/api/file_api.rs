@@ -1,55 +1,479 @@
 use crate::error::AppError;
-use crate::tools::s3::S3;
-use axum::extract::{Multipart, Path, State};
-use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
-use axum::http::HeaderMap;
-use axum::response::IntoResponse;
+use crate::tools::inspect::{self, ComponentDescription, ComponentInfo};
+use crate::tools::{adapt, alias, compose, config, names, registry};
+use crate::tools::runtime::Runtime;
+use crate::tools::registry::FunctionVersion;
+use crate::tools::s3::{
+    encode_key, GetOptions, ObjectInfo, ObjectMeta, PresignedRequest, PutResult, S3,
+};
+use crate::tools::signing::Verifier;
+use crate::tools::unix_now;
+use crate::tools::upload::{staging_key, StreamedUpload};
+use crate::tools::validate::{self, Policy};
+use aws_sdk_s3::primitives::ByteStream;
+use axum::body::Body;
+use axum::extract::{FromRequest, Multipart, Path, Query, Request, State};
+use axum::http::header::{
+    ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+    IF_NONE_MATCH, LAST_MODIFIED, RANGE,
+};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio_util::io::ReaderStream;
 
+#[derive(Deserialize)]
+pub struct VersionQuery {
+    pub version: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ComposePart {
+    /// Defaults to the bucket of the composed function
+    pub bucket: Option<String>,
+    /// `key@alias` is resolved once, the composition does not follow later alias changes
+    pub key: String,
+    pub version: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ComposeRequest {
+    /// Stages followed by the function they wrap, the first part receives the input
+    pub parts: Vec<ComposePart>,
+}
+
+#[derive(Deserialize)]
+pub struct UploadQuery {
+    /// Componentize `wasm32-wasip1` core modules with the bundled reactor adapter
+    #[serde(default = "default_adapt")]
+    pub adapt: bool,
+}
+
+fn default_adapt() -> bool {
+    true
+}
+
+/// Upload headers stored as user defined object metadata
+const META_HEADER_PREFIX: &str = "x-faas-meta-";
+
+/// S3 limits user defined metadata to 2 KB
+const MAX_USER_META: usize = 2048;
+
+const WASM_CONTENT_TYPE: &str = "application/wasm";
+
+/// Presigned URLs expire after 15 minutes unless `ttl` asks otherwise
+const DEFAULT_PRESIGN_TTL: u64 = 15 * 60;
+
+/// Upper bound of SigV4 presigned URLs
+const MAX_PRESIGN_TTL: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum PresignOp {
+    Put,
+    Get,
+}
+
+#[derive(Deserialize)]
+pub struct PresignQuery {
+    pub op: PresignOp,
+    /// Lifetime of the URL in seconds
+    pub ttl: Option<u64>,
+    pub version: Option<String>,
+    /// Size in bytes of the file, required for `op=put`
+    pub size: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct PresignResponse {
+    #[serde(flatten)]
+    pub request: PresignedRequest,
+    pub expires_at: u64,
+    /// Id to pass to the finalize endpoint once the presigned PUT is done
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upload_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finalize: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FinalizeQuery {
+    pub upload_id: String,
+    #[serde(default = "default_adapt")]
+    pub adapt: bool,
+}
+
+#[derive(Serialize)]
+pub struct UploadResponse {
+    pub message: String,
+    pub version: Option<String>,
+    pub etag: Option<String>,
+    pub sha256: String,
+    pub size: u64,
+    pub adapted: bool,
+}
+
+/// Accepts a multipart form with a `file` field or a raw body (`application/wasm`)
 pub async fn upload_file(
     State(s3): State<Arc<S3>>,
+    State(runtime): State<Arc<Runtime>>,
+    State(policy): State<Arc<Policy>>,
+    State(verifier): State<Arc<Verifier>>,
     Path((bucket, key)): Path<(String, String)>,
-    mut multipart: Multipart,
-) -> Result<Json<String>, AppError> {
-    while let Some(field) = multipart.next_field().await.map_err(|e| AppError::Internal(e.to_string()))? {
-        let name = field.name().unwrap_or_default().to_string();
-        if name == "file" {
-
-            // Set a reasonable size limit (50MB) to prevent memory issues
-            // TODO: handle larger files already > 1 MB (see S3 FAAS)
-            let data = field.bytes().await.map_err(|e| AppError::Internal(format!("Failed to read file data: {}", e)))?;
-            
-            // Check file size to prevent overly large uploads
-            if data.len() > 50 * 1024 * 1024 {
-                return Err(AppError::Internal("File too large. Maximum size is 50MB".to_string()));
+    Query(query): Query<UploadQuery>,
+    request: Request,
+) -> Result<Json<UploadResponse>, AppError> {
+    let mut upload = StreamedUpload::new(s3.clone(), &bucket, &key, policy.max_size);
+
+    let context = UploadContext {
+        s3: s3.clone(),
+        runtime,
+        policy,
+        verifier,
+    };
+    let result = store_upload(&context, &bucket, &key, &query, &mut upload, request).await;
+    if result.is_err()
+        && let Err(e) = upload.abort().await
+    {
+        tracing::warn!("Failed to abort upload of {}: {}", key, e);
+    }
+
+    result.map(Json)
+}
+
+#[derive(Clone)]
+struct UploadContext {
+    s3: Arc<S3>,
+    runtime: Arc<Runtime>,
+    policy: Arc<Policy>,
+    verifier: Arc<Verifier>,
+}
+
+async fn store_upload(
+    context: &UploadContext,
+    bucket: &str,
+    key: &str,
+    query: &UploadQuery,
+    upload: &mut StreamedUpload,
+    request: Request,
+) -> Result<UploadResponse, AppError> {
+    let s3 = &context.s3;
+
+    let signature = request
+        .headers()
+        .get("x-faas-signature")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_lowercase());
+    let user = user_metadata(request.headers())?;
+
+    receive_upload(upload, request, user).await?;
+
+    let data = upload.data().await?;
+    let (data, checked) = check_module(context, data, upload.sha256(), query.adapt, signature.as_deref()).await?;
+    drop(data);
+
+    let (stored, size) = match &checked.adapted {
+        Some(bytes) => {
+            upload.abort().await?;
+            let meta = adapted_meta(upload.meta());
+            let stored = s3.upload_file(bucket, key, ByteStream::from(bytes.clone()), &meta).await?;
+            (stored, bytes.len() as u64)
+        }
+        None => (upload.complete().await?, upload.size()),
+    };
+
+    register(s3, bucket, key, stored, size, checked, signature).await
+}
+
+/// Adapted modules are components, whatever the upload declared
+fn adapted_meta(meta: &ObjectMeta) -> ObjectMeta {
+    ObjectMeta {
+        content_type: Some(WASM_CONTENT_TYPE.to_string()),
+        ..meta.clone()
+    }
+}
+
+/// Collects the `x-faas-meta-*` headers of an upload
+fn user_metadata(headers: &HeaderMap) -> Result<BTreeMap<String, String>, AppError> {
+    let mut user = BTreeMap::new();
+    for (name, value) in headers {
+        let Some(name) = name.as_str().strip_prefix(META_HEADER_PREFIX) else {
+            continue;
+        };
+        if name.is_empty() || name == "filename" {
+            return Err(AppError::BadRequest(format!(
+                "Invalid metadata header {}{}",
+                META_HEADER_PREFIX, name
+            )));
+        }
+        let value = value
+            .to_str()
+            .map_err(|_| AppError::BadRequest(format!("Metadata {} must be ASCII", name)))?;
+        user.insert(name.to_string(), value.to_string());
+    }
+
+    let size: usize = user.iter().map(|(k, v)| k.len() + v.len()).sum();
+    if size > MAX_USER_META {
+        return Err(AppError::BadRequest(format!(
+            "Metadata exceeds {} bytes",
+            MAX_USER_META
+        )));
+    }
+    Ok(user)
+}
+
+/// Outcome of the checks shared by direct and presigned uploads
+struct CheckedModule {
+    adapted: Option<Vec<u8>>,
+    info: ComponentInfo,
+    sha256: String,
+}
+
+/// Adapting and validating compile the module, so the checks run on a blocking thread.
+/// The bytes are handed back for callers that store them.
+async fn check_module(
+    context: &UploadContext,
+    data: Vec<u8>,
+    sha256: String,
+    adapt: bool,
+    signature: Option<&str>,
+) -> Result<(Vec<u8>, CheckedModule), AppError> {
+    let context = context.clone();
+    let signature = signature.map(str::to_string);
+    tokio::task::spawn_blocking(move || {
+        let checked = run_checks(&context, &data, sha256, adapt, signature.as_deref())?;
+        Ok((data, checked))
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+fn run_checks(
+    context: &UploadContext,
+    data: &[u8],
+    sha256: String,
+    adapt: bool,
+    signature: Option<&str>,
+) -> Result<CheckedModule, AppError> {
+    validate::check_size(&context.policy, data)?;
+    let adapted = if adapt && adapt::is_adaptable(data) {
+        Some(adapt::adapt(data)?)
+    } else {
+        None
+    };
+
+    // Size, component validity, world and capability checks, broken modules never reach S3
+    let info = validate::validate(&context.runtime, &context.policy, adapted.as_deref().unwrap_or(data))?.info();
+
+    // The signature covers the stored bytes, which differ from the upload once adapted
+    if adapted.is_some() && signature.is_some() {
+        return Err(AppError::Validation(
+            "Signed uploads have to be components, adapt the module before signing it".to_string(),
+        ));
+    }
+    let sha256 = match &adapted {
+        Some(bytes) => registry::sha256_hex(bytes),
+        None => sha256,
+    };
+    context.verifier.verify(&sha256, signature)?;
+
+    Ok(CheckedModule { adapted, info, sha256 })
+}
+
+async fn register(
+    s3: &S3,
+    bucket: &str,
+    key: &str,
+    stored: PutResult,
+    size: u64,
+    checked: CheckedModule,
+    signature: Option<String>,
+) -> Result<UploadResponse, AppError> {
+    let version = FunctionVersion {
+        version: stored.version.clone(),
+        size,
+        sha256: checked.sha256.clone(),
+        signature,
+        created_at: unix_now(),
+    };
+    registry::record_upload(s3, bucket, key, version, checked.info).await?;
+
+    let mut message = match &stored.version {
+        Some(version) => format!("File {} uploaded to {} (version {})", key, bucket, version),
+        None => format!("File {} uploaded to {}", key, bucket),
+    };
+    if checked.adapted.is_some() {
+        message.push_str(", adapted from core module");
+    }
+
+    Ok(UploadResponse {
+        message,
+        version: stored.version,
+        etag: stored.etag,
+        sha256: checked.sha256,
+        size,
+        adapted: checked.adapted.is_some(),
+    })
+}
+
+async fn receive_upload(
+    upload: &mut StreamedUpload,
+    request: Request,
+    user: BTreeMap<String, String>,
+) -> Result<(), AppError> {
+    let content_type = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
+    if !content_type
+        .as_deref()
+        .is_some_and(|v| v.starts_with("multipart/form-data"))
+    {
+        upload.set_meta(ObjectMeta {
+            content_type,
+            filename: None,
+            user,
+        });
+
+        let mut stream = request.into_body().into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| AppError::Internal(format!("Failed to read file data: {}", e)))?;
+            upload.write(&chunk).await?;
+        }
+        return Ok(());
+    }
+
+    let mut multipart = Multipart::from_request(request, &())
+        .await
+        .map_err(|e| AppError::BadRequest(e.body_text()))?;
+
+    while let Some(mut field) = multipart.next_field().await.map_err(|e| AppError::Internal(e.to_string()))? {
+        if field.name() == Some("file") {
+            upload.set_meta(ObjectMeta {
+                content_type: field.content_type().map(|v| v.to_string()),
+                filename: field.file_name().map(|v| v.to_string()),
+                user,
+            });
+            while let Some(chunk) = field
+                .chunk()
+                .await
+                .map_err(|e| AppError::Internal(format!("Failed to read file data: {}", e)))?
+            {
+                upload.write(&chunk).await?;
             }
-            
-            let body = aws_sdk_s3::primitives::ByteStream::from(data.to_vec());
-            s3.upload_file(&bucket, &key, body).await?;
-            return Ok(Json(format!("File {} uploaded to {}", key, bucket)));
+            return Ok(());
         }
     }
 
-    Err(AppError::Internal("Missing file in multipart".to_string()))
+    Err(AppError::BadRequest("Missing file in multipart".to_string()))
 }
 
+/// Streams an object with its stored headers, supports `Range` and `If-None-Match`
 pub async fn download_file(
     State(s3): State<Arc<S3>>,
     Path((bucket, key)): Path<(String, String)>,
-) -> Result<impl IntoResponse, AppError> {
-    let body: aws_sdk_s3::primitives::ByteStream = s3.download_file(&bucket, &key).await?;
+    Query(query): Query<VersionQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let options = GetOptions {
+        version: query.version.as_deref(),
+        range: headers.get(RANGE).and_then(|v| v.to_str().ok()),
+        if_none_match: headers.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()),
+    };
+
+    let Some((info, body)) = s3.get_object(&bucket, &key, &options).await? else {
+        return Ok(StatusCode::NOT_MODIFIED.into_response());
+    };
+
+    let status = match info.content_range {
+        Some(_) => StatusCode::PARTIAL_CONTENT,
+        None => StatusCode::OK,
+    };
+    let stream = ReaderStream::new(body.into_async_read());
+    Ok((status, object_headers(&key, &info), Body::from_stream(stream)).into_response())
+}
+
+pub async fn head_file(
+    State(s3): State<Arc<S3>>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<VersionQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let options = GetOptions {
+        version: query.version.as_deref(),
+        range: None,
+        if_none_match: headers.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()),
+    };
+
+    match s3.head_object(&bucket, &key, &options).await? {
+        Some(info) => Ok((object_headers(&key, &info), Body::empty()).into_response()),
+        None => Ok(StatusCode::NOT_MODIFIED.into_response()),
+    }
+}
 
+fn object_headers(key: &str, info: &ObjectInfo) -> HeaderMap {
     let mut headers = HeaderMap::new();
-    headers.insert(CONTENT_TYPE, "application/octet-stream".parse().unwrap());
-    headers.insert(
+    let mut insert = |name: HeaderName, value: &str| {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            headers.insert(name, value);
+        }
+    };
+
+    insert(
+        CONTENT_TYPE,
+        info.meta.content_type.as_deref().unwrap_or("application/octet-stream"),
+    );
+    insert(CONTENT_LENGTH, &info.size.to_string());
+    insert(ACCEPT_RANGES, "bytes");
+    insert(
         CONTENT_DISPOSITION,
-        format!("attachment; filename=\"{}\"", key).parse().unwrap(),
+        &content_disposition(info.meta.filename.as_deref().unwrap_or(key)),
     );
+    if let Some(etag) = &info.etag {
+        insert(ETAG, etag);
+    }
+    if let Some(last_modified) = &info.last_modified {
+        insert(LAST_MODIFIED, last_modified);
+    }
+    if let Some(range) = &info.content_range {
+        insert(CONTENT_RANGE, range);
+    }
+    if let Some(version) = &info.version {
+        insert(HeaderName::from_static("x-faas-version"), version);
+    }
+    for (name, value) in &info.meta.user {
+        if let Ok(name) = HeaderName::from_bytes(format!("{}{}", META_HEADER_PREFIX, name).as_bytes()) {
+            insert(name, value);
+        }
+    }
 
-    let stream = ReaderStream::new(body.into_async_read());
-    Ok((headers, axum::body::Body::from_stream(stream)))
+    headers
+}
+
+/// `attachment` with an ASCII fallback name and the RFC 5987 encoded original
+fn content_disposition(filename: &str) -> String {
+    let name = filename.rsplit('/').next().unwrap_or(filename);
+    let fallback: String = name
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii_graphic() || c == ' ' => c,
+            _ => '_',
+        })
+        .collect();
+
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback,
+        encode_key(name)
+    )
 }
 
 pub async fn delete_file(
@@ -57,5 +481,263 @@ pub async fn delete_file(
     Path((bucket, key)): Path<(String, String)>,
 ) -> Result<Json<String>, AppError> {
     s3.delete_file(&bucket, &key).await?;
+    registry::remove(&s3, &bucket, &key).await?;
+    config::remove(&s3, &bucket, &key).await?;
+    alias::remove(&s3, &bucket, &key).await?;
     Ok(Json(format!("File {} deleted from {}", key, bucket)))
-}
\ No newline at end of file
+}
+
+/// Loads the component with the server's engine and reports what it imports and exports
+pub async fn describe_file(
+    State(s3): State<Arc<S3>>,
+    State(runtime): State<Arc<Runtime>>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<VersionQuery>,
+) -> Result<Json<ComponentDescription>, AppError> {
+    let wasm_bytes = s3
+        .download_bytes(&bucket, &key, query.version.as_deref())
+        .await?;
+
+    // Describing compiles the component, which would stall the worker thread
+    let description = tokio::task::spawn_blocking(move || inspect::describe(&runtime, &wasm_bytes))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))??;
+    Ok(Json(description))
+}
+
+/// Presigned URL for a direct upload to S3 (`op=put`) or download from S3 (`op=get`)
+pub async fn presign_file(
+    State(s3): State<Arc<S3>>,
+    State(policy): State<Arc<Policy>>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<PresignQuery>,
+) -> Result<Json<PresignResponse>, AppError> {
+    let ttl = query.ttl.unwrap_or(DEFAULT_PRESIGN_TTL);
+    if ttl == 0 || ttl > MAX_PRESIGN_TTL {
+        return Err(AppError::BadRequest(format!(
+            "ttl must be between 1 and {} seconds",
+            MAX_PRESIGN_TTL
+        )));
+    }
+    let expires_in = Duration::from_secs(ttl);
+
+    let response = match query.op {
+        PresignOp::Get => PresignResponse {
+            request: s3
+                .presign_get(&bucket, &key, query.version.as_deref(), expires_in)
+                .await?,
+            expires_at: unix_now() + ttl,
+            upload_id: None,
+            finalize: None,
+        },
+        PresignOp::Put => {
+            // The signed content length keeps the staged object within the size limit
+            let size = query
+                .size
+                .filter(|size| *size > 0)
+                .ok_or_else(|| AppError::BadRequest("op=put needs the size of the file".to_string()))?;
+            if size > policy.max_size as u64 {
+                return Err(AppError::Validation(format!(
+                    "File too large. Maximum size is {}MB",
+                    policy.max_size / 1024 / 1024
+                )));
+            }
+            let upload_id = format!("{:032x}", rand::random::<u128>());
+            PresignResponse {
+                request: s3
+                    .presign_put(&bucket, &staging_key(&upload_id, &key), size as i64, expires_in)
+                    .await?,
+                expires_at: unix_now() + ttl,
+                finalize: Some(format!(
+                    "/file/{}/{}/finalize?upload_id={}",
+                    bucket,
+                    encode_key(&key),
+                    upload_id
+                )),
+                upload_id: Some(upload_id),
+            }
+        }
+    };
+
+    Ok(Json(response))
+}
+
+/// Validates and registers an object uploaded through a presigned PUT
+pub async fn finalize_upload(
+    State(s3): State<Arc<S3>>,
+    State(runtime): State<Arc<Runtime>>,
+    State(policy): State<Arc<Policy>>,
+    State(verifier): State<Arc<Verifier>>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<FinalizeQuery>,
+    headers: HeaderMap,
+) -> Result<Json<UploadResponse>, AppError> {
+    if query.upload_id.is_empty() || !query.upload_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::BadRequest("Invalid upload_id".to_string()));
+    }
+    let staged = staging_key(&query.upload_id, &key);
+
+    let signature = headers
+        .get("x-faas-signature")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_lowercase());
+
+    let context = UploadContext {
+        s3: s3.clone(),
+        runtime,
+        policy,
+        verifier,
+    };
+    let result = finalize_staged(&context, &bucket, &key, &staged, query.adapt, signature).await;
+
+    // The staged object is dropped either way, a rejected upload has to be presigned again
+    if let Err(e) = s3.purge(&bucket, &staged).await {
+        tracing::warn!("Failed to remove staged upload {}: {}", staged, e);
+    }
+
+    result.map(Json)
+}
+
+async fn finalize_staged(
+    context: &UploadContext,
+    bucket: &str,
+    key: &str,
+    staged: &str,
+    adapt: bool,
+    signature: Option<String>,
+) -> Result<UploadResponse, AppError> {
+    let s3 = &context.s3;
+
+    let info = s3
+        .head_object(bucket, staged, &GetOptions::default())
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("No staged upload for {}", key)))?;
+    let too_large = || {
+        AppError::Validation(format!(
+            "File too large. Maximum size is {}MB",
+            context.policy.max_size / 1024 / 1024
+        ))
+    };
+    if info.size as usize > context.policy.max_size {
+        return Err(too_large());
+    }
+
+    // The presigned URL stays valid, the staged object may change after the head above.
+    // Only the bytes validated here are stored, never the staged object itself
+    let data = s3
+        .download_limited(bucket, staged, context.policy.max_size)
+        .await?
+        .ok_or_else(too_large)?;
+    let sha256 = registry::sha256_hex(&data);
+    let (data, checked) = check_module(context, data, sha256, adapt, signature.as_deref()).await?;
+
+    let (bytes, meta) = match &checked.adapted {
+        Some(bytes) => (bytes.clone(), adapted_meta(&info.meta)),
+        None => (data, info.meta.clone()),
+    };
+    let size = bytes.len() as u64;
+    let stored = s3.upload_file(bucket, key, ByteStream::from(bytes), &meta).await?;
+
+    register(s3, bucket, key, stored, size, checked, signature).await
+}
+
+/// Composes stored stages and a function into one component stored at `key`
+pub async fn compose_file(
+    State(s3): State<Arc<S3>>,
+    State(runtime): State<Arc<Runtime>>,
+    State(policy): State<Arc<Policy>>,
+    State(verifier): State<Arc<Verifier>>,
+    Path((bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(request): Json<ComposeRequest>,
+) -> Result<Json<UploadResponse>, AppError> {
+    if request.parts.len() < 2 || request.parts.len() > compose::MAX_PARTS {
+        return Err(AppError::BadRequest(format!(
+            "A composition needs 2 to {} parts",
+            compose::MAX_PARTS
+        )));
+    }
+
+    let context = UploadContext {
+        s3: s3.clone(),
+        runtime: runtime.clone(),
+        policy,
+        verifier,
+    };
+
+    let count = request.parts.len();
+    let mut parts = Vec::with_capacity(count);
+    for (index, part) in request.parts.iter().enumerate() {
+        let bytes = load_part(&context, &bucket, part).await?;
+        // Parsing and composing up to 16 components would stall the worker thread
+        let described = runtime.clone();
+        let (bytes, description) = tokio::task::spawn_blocking(move || {
+            let description = inspect::describe(&described, &bytes);
+            (bytes, description)
+        })
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+        compose::check_world(index, count, &part.key, description?.world.as_deref())?;
+        parts.push(bytes);
+    }
+
+    let signature = headers
+        .get("x-faas-signature")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_lowercase());
+    // The composed module is checked in the same task, its validation compiles it again
+    let checking = context.clone();
+    let checked_signature = signature.clone();
+    let (composed, checked) = tokio::task::spawn_blocking(move || {
+        let composed = compose::compose(&parts)?;
+        let sha256 = registry::sha256_hex(&composed);
+        let checked = run_checks(&checking, &composed, sha256, false, checked_signature.as_deref())?;
+        Ok::<_, AppError>((composed, checked))
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))??;
+
+    let meta = ObjectMeta {
+        content_type: Some(WASM_CONTENT_TYPE.to_string()),
+        ..ObjectMeta::default()
+    };
+    let size = composed.len() as u64;
+    let stored = s3.upload_file(&bucket, &key, ByteStream::from(composed), &meta).await?;
+
+    let mut response = register(&s3, &bucket, &key, stored, size, checked, signature).await?;
+    response.message = format!("{}, composed from {} parts", response.message, count);
+    Ok(Json(response))
+}
+
+/// Bytes of a part, checked against the digest and signature recorded on upload
+async fn load_part(
+    context: &UploadContext,
+    default_bucket: &str,
+    part: &ComposePart,
+) -> Result<Vec<u8>, AppError> {
+    let s3 = &context.s3;
+    let bucket = part.bucket.as_deref().unwrap_or(default_bucket);
+    let (key, alias) = alias::split_alias(&part.key);
+    names::validate_object(bucket, key)?;
+
+    let version = match (part.version.clone(), alias) {
+        (Some(version), _) => Some(version),
+        (None, Some(alias)) => alias::resolve(s3, bucket, key, alias).await?,
+        (None, None) => None,
+    };
+
+    let meta = registry::load(s3, bucket, key).await?;
+    let recorded = meta.as_ref().and_then(|m| m.version_for(version.as_deref()));
+    context.verifier.verify_function(key, recorded)?;
+
+    let bytes = s3.download_bytes(bucket, key, version.as_deref()).await?;
+    if let Some(recorded) = recorded
+        && registry::sha256_hex(&bytes) != recorded.sha256
+    {
+        return Err(AppError::Forbidden(format!(
+            "{} does not match the digest recorded on upload",
+            part.key
+        )));
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,41 @@
+use crate::error::AppError;
+use crate::tools::jobs::{Job, Jobs};
+use crate::tools::webhook::{Callback, DeliveryAttempt, DeliveryStatus};
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub struct DeliveriesResponse {
+    pub callback: Option<Callback>,
+    pub status: Option<DeliveryStatus>,
+    pub attempts: Vec<DeliveryAttempt>,
+}
+
+pub async fn get_job(
+    State(jobs): State<Arc<Jobs>>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, AppError> {
+    Ok(Json(jobs.get(&id).await?.redacted()))
+}
+
+pub async fn cancel_job(
+    State(jobs): State<Arc<Jobs>>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, AppError> {
+    Ok(Json(jobs.cancel(&id).await?.redacted()))
+}
+
+/// Callback attempts of a job, with status code or error of each attempt
+pub async fn get_deliveries(
+    State(jobs): State<Arc<Jobs>>,
+    Path(id): Path<String>,
+) -> Result<Json<DeliveriesResponse>, AppError> {
+    let job = jobs.get(&id).await?.redacted();
+    Ok(Json(DeliveriesResponse {
+        callback: job.callback,
+        status: job.delivery,
+        attempts: job.deliveries,
+    }))
+}
@@ -1,3 +1,18 @@
 pub mod bucket_api;
 pub mod file_api;
-pub mod exec_api;
\ No newline at end of file
+pub mod exec_api;
+pub mod alias_api;
+pub mod registry_api;
+pub mod version_api;
+pub mod jobs_api;
+pub mod schedule_api;
+pub mod trigger_api;
+pub mod pipeline_api;
+pub mod workflow_api;
+pub mod config_api;
+pub mod routes;
+
+/// Serde default of the `enabled` flag of schedules and triggers
+fn default_enabled() -> bool {
+    true
+}
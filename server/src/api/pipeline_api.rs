@@ -0,0 +1,70 @@
+use crate::error::AppError;
+use crate::tools::pipeline::{Pipeline, PipelineRun, Pipelines, Step, StepStatus};
+use crate::tools::unix_now;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct PipelineRequest {
+    #[serde(default)]
+    pub description: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+pub async fn list_pipelines(
+    State(pipelines): State<Arc<Pipelines>>,
+) -> Result<Json<Vec<Pipeline>>, AppError> {
+    Ok(Json(pipelines.list().await?))
+}
+
+pub async fn get_pipeline(
+    State(pipelines): State<Arc<Pipelines>>,
+    Path(name): Path<String>,
+) -> Result<Json<Pipeline>, AppError> {
+    Ok(Json(pipelines.get(&name).await?))
+}
+
+pub async fn put_pipeline(
+    State(pipelines): State<Arc<Pipelines>>,
+    Path(name): Path<String>,
+    Json(request): Json<PipelineRequest>,
+) -> Result<Json<Pipeline>, AppError> {
+    let now = unix_now();
+    let pipeline = Pipeline {
+        name,
+        description: request.description,
+        steps: request.steps,
+        created_at: now,
+        updated_at: now,
+    };
+    Ok(Json(pipelines.put(pipeline).await?))
+}
+
+pub async fn delete_pipeline(
+    State(pipelines): State<Arc<Pipelines>>,
+    Path(name): Path<String>,
+) -> Result<Json<String>, AppError> {
+    pipelines.delete(&name).await?;
+    Ok(Json(format!("Pipeline {} deleted", name)))
+}
+
+/// Runs the pipeline, a stopped pipeline answers with the status of the failed step
+pub async fn run_pipeline(
+    State(pipelines): State<Arc<Pipelines>>,
+    Path(name): Path<String>,
+    Json(payload): Json<serde_json::Value>,
+) -> Result<Response, AppError> {
+    let run: PipelineRun = pipelines.run(&name, payload).await?;
+
+    let status = match (&run.status, &run.error) {
+        (StepStatus::Failed, Some(error)) => {
+            StatusCode::from_u16(error.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        _ => StatusCode::OK,
+    };
+    Ok((status, Json(run)).into_response())
+}
@@ -0,0 +1,73 @@
+use crate::error::AppError;
+use crate::tools::names;
+use crate::tools::registry::{self, FunctionMetadata, Limits};
+use crate::tools::s3::S3;
+use crate::tools::unix_now;
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Fields of the registry entry that are maintained by the owners of a function
+#[derive(Deserialize)]
+pub struct FunctionUpdate {
+    pub description: Option<String>,
+    pub owner: Option<String>,
+    pub limits: Option<Limits>,
+}
+
+pub async fn list_functions(
+    State(s3): State<Arc<S3>>,
+    Path(namespace): Path<String>,
+) -> Result<Json<Vec<FunctionMetadata>>, AppError> {
+    names::validate_bucket(&namespace)?;
+    Ok(Json(registry::list(&s3, &namespace).await?))
+}
+
+pub async fn get_function(
+    State(s3): State<Arc<S3>>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> Result<Json<FunctionMetadata>, AppError> {
+    names::validate_object(&namespace, &name)?;
+    registry::load(&s3, &namespace, &name)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("Function {} not found in {}", name, namespace)))
+}
+
+pub async fn put_function(
+    State(s3): State<Arc<S3>>,
+    Path((namespace, name)): Path<(String, String)>,
+    Json(update): Json<FunctionUpdate>,
+) -> Result<Json<FunctionMetadata>, AppError> {
+    names::validate_object(&namespace, &name)?;
+    let _updates = registry::lock_updates().await;
+    let mut meta = registry::load(&s3, &namespace, &name)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Function {} not found in {}", name, namespace)))?;
+
+    if update.description.is_some() {
+        meta.description = update.description;
+    }
+    if update.owner.is_some() {
+        meta.owner = update.owner;
+    }
+    if let Some(limits) = update.limits {
+        // Clients may narrow the buckets a function calls into, widening them is left to operators
+        if let Some(bucket) = limits
+            .call_buckets
+            .iter()
+            .find(|b| !meta.limits.call_buckets.contains(b))
+        {
+            return Err(AppError::Forbidden(format!(
+                "Calls from {} into {} can't be granted through the API",
+                name, bucket
+            )));
+        }
+        meta.limits = limits;
+    }
+    meta.updated_at = unix_now();
+
+    registry::save(&s3, &meta).await?;
+    Ok(Json(meta))
+}
@@ -0,0 +1,149 @@
+use crate::api::file_api::{
+    compose_file, delete_file, describe_file, download_file, finalize_upload, head_file,
+    presign_file, upload_file,
+};
+use crate::api::version_api::{delete_version, list_versions, restore_version};
+use crate::error::AppError;
+use crate::tools::names::{self, FileRoute};
+use crate::AppState;
+use axum::extract::{FromRequest, Path, Query, Request, State};
+use axum::http::{Method, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+
+// `{*path}` has to be the last segment of a route, so the sub-resources of a file
+// (`describe`, `versions`, `presign`, `finalize`, `compose`) are dispatched here.
+
+pub async fn get_file(
+    State(state): State<AppState>,
+    Path((bucket, path)): Path<(String, String)>,
+    request: Request,
+) -> Result<Response, AppError> {
+    names::validate_bucket(&bucket)?;
+    let (parts, _) = request.into_parts();
+
+    match FileRoute::parse(&path)? {
+        FileRoute::Object(key) => {
+            download_file(State(state.s3), Path((bucket, key)), query(&parts.uri)?, parts.headers).await
+        }
+        FileRoute::Describe(key) => {
+            describe_file(State(state.s3), State(state.runtime), Path((bucket, key)), query(&parts.uri)?)
+                .await
+                .map(IntoResponse::into_response)
+        }
+        FileRoute::Versions(key) => list_versions(State(state.s3), Path((bucket, key)))
+            .await
+            .map(IntoResponse::into_response),
+        _ => Err(not_allowed(&parts.method, &path)),
+    }
+}
+
+pub async fn head_file_route(
+    State(state): State<AppState>,
+    Path((bucket, path)): Path<(String, String)>,
+    request: Request,
+) -> Result<Response, AppError> {
+    names::validate_bucket(&bucket)?;
+    let (parts, _) = request.into_parts();
+
+    match FileRoute::parse(&path)? {
+        FileRoute::Object(key) => {
+            head_file(State(state.s3), Path((bucket, key)), query(&parts.uri)?, parts.headers).await
+        }
+        _ => Err(not_allowed(&parts.method, &path)),
+    }
+}
+
+pub async fn post_file(
+    State(state): State<AppState>,
+    Path((bucket, path)): Path<(String, String)>,
+    request: Request,
+) -> Result<Response, AppError> {
+    names::validate_bucket(&bucket)?;
+    let uri = request.uri().clone();
+
+    match FileRoute::parse(&path)? {
+        FileRoute::Object(key) => upload_file(
+            State(state.s3),
+            State(state.runtime),
+            State(state.policy),
+            State(state.verifier),
+            Path((bucket, key)),
+            query(&uri)?,
+            request,
+        )
+        .await
+        .map(IntoResponse::into_response),
+        FileRoute::Presign(key) => presign_file(
+            State(state.s3),
+            State(state.policy),
+            Path((bucket, key)),
+            query(&uri)?,
+        )
+        .await
+        .map(IntoResponse::into_response),
+        FileRoute::Finalize(key) => finalize_upload(
+            State(state.s3),
+            State(state.runtime),
+            State(state.policy),
+            State(state.verifier),
+            Path((bucket, key)),
+            query(&uri)?,
+            request.headers().clone(),
+        )
+        .await
+        .map(IntoResponse::into_response),
+        FileRoute::Compose(key) => {
+            let headers = request.headers().clone();
+            let body = Json::from_request(request, &())
+                .await
+                .map_err(|e| AppError::BadRequest(e.body_text()))?;
+            compose_file(
+                State(state.s3),
+                State(state.runtime),
+                State(state.policy),
+                State(state.verifier),
+                Path((bucket, key)),
+                headers,
+                body,
+            )
+            .await
+            .map(IntoResponse::into_response)
+        }
+        FileRoute::Restore(key, version) => {
+            restore_version(State(state.s3), Path((bucket, key, version)))
+                .await
+                .map(IntoResponse::into_response)
+        }
+        _ => Err(not_allowed(request.method(), &path)),
+    }
+}
+
+pub async fn delete_file_route(
+    State(state): State<AppState>,
+    Path((bucket, path)): Path<(String, String)>,
+    method: Method,
+) -> Result<Response, AppError> {
+    names::validate_bucket(&bucket)?;
+
+    match FileRoute::parse(&path)? {
+        FileRoute::Object(key) => delete_file(State(state.s3), Path((bucket, key)))
+            .await
+            .map(IntoResponse::into_response),
+        FileRoute::Version(key, version) => {
+            delete_version(State(state.s3), Path((bucket, key, version)))
+                .await
+                .map(IntoResponse::into_response)
+        }
+        _ => Err(not_allowed(&method, &path)),
+    }
+}
+
+fn query<T: DeserializeOwned>(uri: &Uri) -> Result<Query<T>, AppError> {
+    Query::try_from_uri(uri).map_err(|e| AppError::BadRequest(e.body_text()))
+}
+
+fn not_allowed(method: &Method, path: &str) -> AppError {
+    AppError::NotFound(format!("No {} route for {}", method, path))
+}
@@ -0,0 +1,81 @@
+use crate::api::default_enabled;
+use crate::error::AppError;
+use crate::tools::scheduler::{MissedRuns, Schedule, ScheduleRun, Scheduler};
+use crate::tools::{alias, names, unix_now};
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct ScheduleRequest {
+    pub cron: String,
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    pub bucket: String,
+    pub key: String,
+    #[serde(default)]
+    pub input: serde_json::Value,
+    #[serde(default)]
+    pub missed: MissedRuns,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+pub async fn list_schedules(State(scheduler): State<Arc<Scheduler>>) -> Json<Vec<Schedule>> {
+    Json(scheduler.list())
+}
+
+pub async fn get_schedule(
+    State(scheduler): State<Arc<Scheduler>>,
+    Path(name): Path<String>,
+) -> Result<Json<Schedule>, AppError> {
+    Ok(Json(scheduler.get(&name)?))
+}
+
+pub async fn put_schedule(
+    State(scheduler): State<Arc<Scheduler>>,
+    Path(name): Path<String>,
+    Json(request): Json<ScheduleRequest>,
+) -> Result<Json<Schedule>, AppError> {
+    let (key, _) = alias::split_alias(&request.key);
+    names::validate_object(&request.bucket, key)?;
+
+    let now = unix_now();
+    let schedule = Schedule {
+        name,
+        cron: request.cron,
+        timezone: request.timezone,
+        bucket: request.bucket,
+        key: request.key,
+        input: request.input,
+        missed: request.missed,
+        enabled: request.enabled,
+        created_at: now,
+        updated_at: now,
+        next_run: None,
+        last_run: None,
+    };
+
+    Ok(Json(scheduler.put(schedule).await?))
+}
+
+pub async fn delete_schedule(
+    State(scheduler): State<Arc<Scheduler>>,
+    Path(name): Path<String>,
+) -> Result<Json<String>, AppError> {
+    scheduler.delete(&name).await?;
+    Ok(Json(format!("Schedule {} deleted", name)))
+}
+
+/// Run history of a schedule, latest runs first
+pub async fn list_runs(
+    State(scheduler): State<Arc<Scheduler>>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<ScheduleRun>>, AppError> {
+    Ok(Json(scheduler.runs(&name).await?))
+}
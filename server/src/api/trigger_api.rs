@@ -0,0 +1,115 @@
+use crate::api::default_enabled;
+use crate::error::AppError;
+use crate::tools::triggers::{EventType, Notification, Trigger, TriggerSource, Triggers};
+use crate::tools::{alias, names, unix_now};
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct TriggerRequest {
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default = "default_events")]
+    pub events: Vec<EventType>,
+    #[serde(default = "default_source")]
+    pub source: TriggerSource,
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: u64,
+    pub target_bucket: String,
+    pub target_key: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_events() -> Vec<EventType> {
+    vec![EventType::Created, EventType::Deleted]
+}
+
+fn default_source() -> TriggerSource {
+    TriggerSource::Poll
+}
+
+fn default_poll_interval() -> u64 {
+    30
+}
+
+#[derive(Serialize)]
+pub struct EventsResponse {
+    /// Records that were object creations or deletions
+    pub events: usize,
+    /// Jobs queued for the matching triggers
+    pub jobs: Vec<String>,
+}
+
+pub async fn list_triggers(State(triggers): State<Arc<Triggers>>) -> Json<Vec<Trigger>> {
+    Json(triggers.list())
+}
+
+pub async fn get_trigger(
+    State(triggers): State<Arc<Triggers>>,
+    Path(name): Path<String>,
+) -> Result<Json<Trigger>, AppError> {
+    Ok(Json(triggers.get(&name)?))
+}
+
+pub async fn put_trigger(
+    State(triggers): State<Arc<Triggers>>,
+    Path(name): Path<String>,
+    Json(request): Json<TriggerRequest>,
+) -> Result<Json<Trigger>, AppError> {
+    let (key, _) = alias::split_alias(&request.target_key);
+    names::validate_object(&request.target_bucket, key)?;
+
+    let now = unix_now();
+    let trigger = Trigger {
+        name,
+        bucket: request.bucket,
+        prefix: request.prefix,
+        events: request.events,
+        source: request.source,
+        poll_interval: request.poll_interval,
+        target_bucket: request.target_bucket,
+        target_key: request.target_key,
+        enabled: request.enabled,
+        created_at: now,
+        updated_at: now,
+    };
+
+    Ok(Json(triggers.put(trigger).await?))
+}
+
+pub async fn delete_trigger(
+    State(triggers): State<Arc<Triggers>>,
+    Path(name): Path<String>,
+) -> Result<Json<String>, AppError> {
+    triggers.delete(&name).await?;
+    Ok(Json(format!("Trigger {} deleted", name)))
+}
+
+/// Receives S3 bucket notifications, protected by `FAAS_EVENTS_TOKEN` when it is set
+pub async fn receive_events(
+    State(triggers): State<Arc<Triggers>>,
+    headers: HeaderMap,
+    Json(notification): Json<Notification>,
+) -> Result<Json<EventsResponse>, AppError> {
+    if let Ok(token) = std::env::var("FAAS_EVENTS_TOKEN") {
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.strip_prefix("Bearer ").unwrap_or(v));
+        if given != Some(token.as_str()) {
+            return Err(AppError::Forbidden("Invalid events token".to_string()));
+        }
+    }
+
+    let mut response = EventsResponse { events: 0, jobs: Vec::new() };
+    for event in notification.records.into_iter().filter_map(|r| r.into_event()) {
+        response.events += 1;
+        response.jobs.extend(triggers.dispatch(event).await?);
+    }
+    Ok(Json(response))
+}
@@ -0,0 +1,100 @@
+use crate::error::AppError;
+use crate::tools::names;
+use crate::tools::registry;
+use crate::tools::s3::{VersionEntry, S3};
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct VersioningRequest {
+    pub enabled: bool,
+}
+
+#[derive(Serialize)]
+pub struct VersioningStatus {
+    pub enabled: bool,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct FunctionVersionEntry {
+    #[serde(flatten)]
+    pub entry: VersionEntry,
+    /// Digest recorded when the version was uploaded through the API
+    pub sha256: Option<String>,
+    pub signed: bool,
+}
+
+#[derive(Serialize)]
+pub struct RestoreResponse {
+    pub message: String,
+    pub version: Option<String>,
+}
+
+pub async fn get_versioning(
+    State(s3): State<Arc<S3>>,
+    Path(bucket): Path<String>,
+) -> Result<Json<VersioningStatus>, AppError> {
+    names::validate_bucket(&bucket)?;
+    let status = s3.get_versioning(&bucket).await?;
+    Ok(Json(VersioningStatus {
+        enabled: status == "Enabled",
+        status,
+    }))
+}
+
+pub async fn put_versioning(
+    State(s3): State<Arc<S3>>,
+    Path(bucket): Path<String>,
+    Json(request): Json<VersioningRequest>,
+) -> Result<Json<VersioningStatus>, AppError> {
+    names::validate_bucket(&bucket)?;
+    s3.put_versioning(&bucket, request.enabled).await?;
+    get_versioning(State(s3), Path(bucket)).await
+}
+
+pub async fn list_versions(
+    State(s3): State<Arc<S3>>,
+    Path((bucket, key)): Path<(String, String)>,
+) -> Result<Json<Vec<FunctionVersionEntry>>, AppError> {
+    let meta = registry::load(&s3, &bucket, &key).await?;
+    let versions = s3.list_versions(&bucket, &key).await?;
+
+    Ok(Json(
+        versions
+            .into_iter()
+            .map(|entry| {
+                let recorded = meta.as_ref().and_then(|m| m.version_for(Some(entry.version.as_str())));
+                FunctionVersionEntry {
+                    sha256: recorded.map(|v| v.sha256.clone()),
+                    signed: recorded.is_some_and(|v| v.signature.is_some()),
+                    entry,
+                }
+            })
+            .collect(),
+    ))
+}
+
+pub async fn restore_version(
+    State(s3): State<Arc<S3>>,
+    Path((bucket, key, version)): Path<(String, String, String)>,
+) -> Result<Json<RestoreResponse>, AppError> {
+    let stored = s3.restore_version(&bucket, &key, &version).await?;
+    registry::record_restore(&s3, &bucket, &key, &version, stored.version.clone()).await?;
+
+    Ok(Json(RestoreResponse {
+        message: format!("Version {} of {} restored as current version", version, key),
+        version: stored.version,
+    }))
+}
+
+pub async fn delete_version(
+    State(s3): State<Arc<S3>>,
+    Path((bucket, key, version)): Path<(String, String, String)>,
+) -> Result<Json<String>, AppError> {
+    s3.delete_version(&bucket, &key, &version).await?;
+    registry::remove_version(&s3, &bucket, &key, &version).await?;
+    Ok(Json(format!("Version {} of {} deleted", version, key)))
+}
@@ -0,0 +1,77 @@
+use crate::error::AppError;
+use crate::tools::unix_now;
+use crate::tools::workflow::{Workflow, WorkflowRun, WorkflowStep, Workflows};
+use axum::extract::{Path, State};
+use axum::http::header::LOCATION;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct WorkflowRequest {
+    #[serde(default)]
+    pub description: Option<String>,
+    pub steps: Vec<WorkflowStep>,
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+pub async fn get_workflow(
+    State(workflows): State<Arc<Workflows>>,
+    Path(name): Path<String>,
+) -> Result<Json<Workflow>, AppError> {
+    Ok(Json(workflows.get(&name).await?))
+}
+
+pub async fn put_workflow(
+    State(workflows): State<Arc<Workflows>>,
+    Path(name): Path<String>,
+    Json(request): Json<WorkflowRequest>,
+) -> Result<Json<Workflow>, AppError> {
+    let now = unix_now();
+    let workflow = Workflow {
+        name,
+        description: request.description,
+        steps: request.steps,
+        output: request.output,
+        created_at: now,
+        updated_at: now,
+    };
+    Ok(Json(workflows.put(workflow).await?))
+}
+
+pub async fn delete_workflow(
+    State(workflows): State<Arc<Workflows>>,
+    Path(name): Path<String>,
+) -> Result<Json<String>, AppError> {
+    workflows.delete(&name).await?;
+    Ok(Json(format!("Workflow {} deleted", name)))
+}
+
+/// Starts a run and answers with `202 Accepted`, the run is at `/workflows/{id}`
+pub async fn start_workflow(
+    State(workflows): State<Arc<Workflows>>,
+    Path(name): Path<String>,
+    Json(payload): Json<serde_json::Value>,
+) -> Result<Response, AppError> {
+    let run = workflows.run(&name, payload).await?;
+    let location = format!("/workflows/{}", run.id);
+    Ok((StatusCode::ACCEPTED, [(LOCATION, location)], Json(run)).into_response())
+}
+
+/// Status of a run with the state of every step
+pub async fn get_workflow_run(
+    State(workflows): State<Arc<Workflows>>,
+    Path(id): Path<String>,
+) -> Result<Json<WorkflowRun>, AppError> {
+    Ok(Json(workflows.status(&id).await?))
+}
+
+pub async fn cancel_workflow_run(
+    State(workflows): State<Arc<Workflows>>,
+    Path(id): Path<String>,
+) -> Result<Json<WorkflowRun>, AppError> {
+    Ok(Json(workflows.cancel(&id).await?))
+}
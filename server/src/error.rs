@@ -18,6 +18,30 @@ pub enum AppError {
     #[error("Internal server error")]
     Internal(String),
 
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Range not satisfiable: {0}")]
+    RangeNotSatisfiable(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Validation error: {0}")]
+    Validation(String),
+
+    #[error("Unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Timeout: {0}")]
+    Timeout(String),
+
     #[error("Wasmtime error: {0}")]
     WasmtimeError(#[from] wasmtime::Error),
 }
@@ -32,19 +56,32 @@ impl AppError {
             err.message().unwrap_or_default().to_string(),
         )
     }
-}
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        println!("{:?}", self);
-        let (status, error_message) = match self {
+    /// HTTP status and message, also recorded for executions that have no response
+    pub fn status_and_message(&self) -> (StatusCode, String) {
+        match self {
             AppError::S3Error(code, msg) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, format!("{}: {}", code, msg))
             }
             AppError::IoError(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
-            AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+            AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone()),
+            AppError::RangeNotSatisfiable(msg) => (StatusCode::RANGE_NOT_SATISFIABLE, msg.clone()),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone()),
+            AppError::Validation(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg.clone()),
+            AppError::Unavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg.clone()),
+            AppError::Timeout(msg) => (StatusCode::GATEWAY_TIMEOUT, msg.clone()),
             AppError::WasmtimeError(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
-        };
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        println!("{:?}", self);
+        let (status, error_message) = self.status_and_message();
 
         let body = Json(json!({
             "error": error_message,
@@ -52,4 +89,4 @@ impl IntoResponse for AppError {
 
         (status, body).into_response()
     }
-}
\ No newline at end of file
+}
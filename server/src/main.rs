@@ -1,34 +1,164 @@
 mod tools;
 mod api;
 mod error;
+#[cfg(test)]
 mod test;
 
-use crate::api::bucket_api::{create_bucket, delete_bucket, list_bucket};
+use crate::api::alias_api::{delete_alias, list_aliases, put_alias, rollback_alias};
+use crate::api::bucket_api::{
+    create_bucket, delete_bucket, get_protection, list_bucket, list_buckets, put_protection,
+};
+use crate::api::config_api::{delete_config, get_config, put_config};
 use crate::api::exec_api::exec_wasm;
-use crate::api::file_api::{delete_file, download_file, upload_file};
+use crate::api::jobs_api::{cancel_job, get_deliveries, get_job};
+use crate::api::pipeline_api::{
+    delete_pipeline, get_pipeline, list_pipelines, put_pipeline, run_pipeline,
+};
+use crate::api::registry_api::{get_function, list_functions, put_function};
+use crate::api::routes::{delete_file_route, get_file, head_file_route, post_file};
+use crate::api::schedule_api::{
+    delete_schedule, get_schedule, list_runs, list_schedules, put_schedule,
+};
+use crate::api::trigger_api::{
+    delete_trigger, get_trigger, list_triggers, put_trigger, receive_events,
+};
+use crate::api::version_api::{get_versioning, put_versioning};
+use crate::api::workflow_api::{
+    cancel_workflow_run, delete_workflow, get_workflow, get_workflow_run, put_workflow,
+    start_workflow,
+};
+use crate::tools::cache::ModuleCache;
+use crate::tools::invoke::Invoker;
+use crate::tools::jobs::Jobs;
+use crate::tools::pipeline::Pipelines;
+use crate::tools::runtime::Runtime;
 use crate::tools::s3::S3;
-use axum::routing::{post, put};
+use crate::tools::scheduler::Scheduler;
+use crate::tools::secrets::Secrets;
+use crate::tools::signing::Verifier;
+use crate::tools::triggers::Triggers;
+use crate::tools::upload;
+use crate::tools::validate::Policy;
+use crate::tools::workflow::Workflows;
+use axum::extract::{DefaultBodyLimit, FromRef};
+use axum::routing::{get, post, put};
 use axum::Router;
 use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+#[derive(Clone, FromRef)]
+pub struct AppState {
+    pub s3: Arc<S3>,
+    pub runtime: Arc<Runtime>,
+    pub policy: Arc<Policy>,
+    pub cache: Arc<ModuleCache>,
+    pub verifier: Arc<Verifier>,
+    pub secrets: Arc<Secrets>,
+    pub invoker: Arc<Invoker>,
+    pub jobs: Arc<Jobs>,
+    pub scheduler: Arc<Scheduler>,
+    pub triggers: Arc<Triggers>,
+    pub pipelines: Arc<Pipelines>,
+    pub workflows: Arc<Workflows>,
+}
+
 #[tokio::main]
 pub async fn main() {
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "doc_store=debug,tower_http=debug".into()),
+                .unwrap_or_else(|_| "doc_store=debug,tower_http=debug,faas::guest=trace".into()),
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    let runtime = Runtime::new().expect("Failed to create the wasmtime runtime");
+    let cache = ModuleCache::new(&runtime).expect("Failed to create the module cache");
+
     let s3 = Arc::new(S3::new().await);
+    let runtime = Arc::new(runtime);
+    let cache = Arc::new(cache);
+    let verifier = Arc::new(Verifier::from_env().expect("Invalid signing configuration"));
+    let secrets = Arc::new(Secrets::from_env().expect("Invalid FAAS_MASTER_KEY"));
+
+    let invoker = Arc::new(Invoker::new(
+        s3.clone(),
+        runtime.clone(),
+        cache.clone(),
+        verifier.clone(),
+        secrets.clone(),
+    ));
+    let jobs = Jobs::start(s3.clone(), invoker.clone())
+        .await
+        .expect("Failed to start the job workers");
+    let scheduler = Scheduler::start(s3.clone(), invoker.clone())
+        .await
+        .expect("Failed to start the scheduler");
+    let triggers = Triggers::start(s3.clone(), jobs.clone())
+        .await
+        .expect("Failed to start the event triggers");
+    upload::start_staging_sweep(s3.clone());
+    let pipelines = Arc::new(Pipelines::new(s3.clone(), invoker.clone()));
+    let workflows = Workflows::start(s3.clone(), invoker.clone())
+        .await
+        .expect("Failed to resume the workflows");
+
+    let state = AppState {
+        s3,
+        runtime,
+        policy: Arc::new(Policy::from_env()),
+        cache,
+        verifier,
+        secrets,
+        invoker,
+        jobs,
+        scheduler,
+        triggers,
+        pipelines,
+        workflows,
+    };
 
     let app = Router::new()
+        .route("/bucket", get(list_buckets))
         .route("/bucket/{bucket}", put(create_bucket).delete(delete_bucket).get(list_bucket))
-        .route("/file/{bucket}/{key}", post(upload_file).get(download_file).delete(delete_file))
-        .route("/exec/{bucket}/{key}", post(exec_wasm))
-        .with_state(s3);
+        .route("/bucket/{bucket}/versioning", get(get_versioning).put(put_versioning))
+        .route("/bucket/{bucket}/protection", get(get_protection).put(put_protection))
+        .route(
+            "/file/{bucket}/{*path}",
+            // The upload enforces its own running limit from the policy
+            post(post_file)
+                .layer(DefaultBodyLimit::disable())
+                .get(get_file)
+                .head(head_file_route)
+                .delete(delete_file_route),
+        )
+        .route("/exec/{bucket}/{*key}", post(exec_wasm))
+        .route("/pipelines", get(list_pipelines))
+        .route(
+            "/pipeline/{name}",
+            post(run_pipeline).get(get_pipeline).put(put_pipeline).delete(delete_pipeline),
+        )
+        .route(
+            "/workflow/{name}",
+            post(start_workflow).get(get_workflow).put(put_workflow).delete(delete_workflow),
+        )
+        .route("/workflows/{id}", get(get_workflow_run).delete(cancel_workflow_run))
+        .route("/jobs/{id}", get(get_job).delete(cancel_job))
+        .route("/jobs/{id}/deliveries", get(get_deliveries))
+        .route("/schedules", get(list_schedules))
+        .route("/schedules/{name}", get(get_schedule).put(put_schedule).delete(delete_schedule))
+        .route("/schedules/{name}/runs", get(list_runs))
+        .route("/triggers", get(list_triggers))
+        .route("/triggers/{name}", get(get_trigger).put(put_trigger).delete(delete_trigger))
+        .route("/events/s3", post(receive_events))
+        .route(
+            "/alias/{bucket}/{*path}",
+            get(list_aliases).put(put_alias).delete(delete_alias).post(rollback_alias),
+        )
+        .route("/functions/{ns}", get(list_functions))
+        .route("/functions/{ns}/{*name}", get(get_function).put(put_function))
+        .route("/config/{ns}/{*name}", get(get_config).put(put_config).delete(delete_config))
+        .with_state(state);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
     tracing::debug!("listening on {}", listener.local_addr().unwrap());
@@ -0,0 +1,40 @@
+#![allow(unused_imports)]
+use crate::error::AppError;
+use crate::tools::adapt::{adapt, is_adaptable};
+
+/// Core module header followed by one custom section, enough for `is_adaptable`
+fn core_module(section: &str, payload: &[u8]) -> Vec<u8> {
+    let mut content = vec![section.len() as u8];
+    content.extend_from_slice(section.as_bytes());
+    content.extend_from_slice(payload);
+    assert!(section.len() < 128 && content.len() < 128, "single byte LEB128 sizes");
+
+    let mut module = b"\0asm\x01\0\0\0".to_vec();
+    module.push(0);
+    module.push(content.len() as u8);
+    module.extend_from_slice(&content);
+    module
+}
+
+#[test]
+fn test_adaptable_modules() {
+    let exec = core_module("component-type:wit-bindgen:0.50.0:local:faas:faas-exec:encoded world", b"");
+    assert!(is_adaptable(&exec));
+
+    // Other worlds, stages included, are not adapted
+    let stage = core_module("component-type:wit-bindgen:0.50.0:local:faas:faas-stage:encoded world", b"");
+    assert!(!is_adaptable(&stage));
+    let other = core_module("component-type:wit-bindgen:0.50.0:wasi:cli:command:encoded world", b"");
+    assert!(!is_adaptable(&other));
+    assert!(!is_adaptable(&core_module("name", b"")));
+
+    // Components and other files are left alone
+    assert!(!is_adaptable(b"\0asm\x0d\0\x01\0"));
+    assert!(!is_adaptable(b"not wasm"));
+}
+
+#[test]
+fn test_adapt_invalid_metadata() {
+    let module = core_module("component-type:wit-bindgen:0.50.0:local:faas:faas-exec:encoded world", b"bogus");
+    assert!(matches!(adapt(&module), Err(AppError::Validation(_))));
+}
@@ -0,0 +1,25 @@
+use crate::tools::alias::{split_alias, Alias, Canary};
+
+#[test]
+fn test_split_alias() {
+    assert_eq!(split_alias("hello_faas.wasm@prod"), ("hello_faas.wasm", Some("prod")));
+    assert_eq!(split_alias("hello_faas.wasm"), ("hello_faas.wasm", None));
+    assert_eq!(split_alias("hello_faas.wasm@"), ("hello_faas.wasm@", None));
+}
+
+#[test]
+fn test_canary_weight() {
+    let alias = Alias {
+        version: "v1".to_string(),
+        canary: Some(Canary {
+            version: "v2".to_string(),
+            weight: 10,
+        }),
+        previous: None,
+    };
+
+    assert_eq!(alias.pick(0), "v2");
+    assert_eq!(alias.pick(9), "v2");
+    assert_eq!(alias.pick(10), "v1");
+    assert_eq!(alias.pick(99), "v1");
+}
@@ -0,0 +1,24 @@
+#![allow(unused_imports)]
+use crate::error::AppError;
+use crate::tools::compose::{check_world, compose};
+use crate::tools::inspect::{FAAS_EXEC_WORLD, FAAS_STAGE_WORLD};
+
+#[test]
+fn test_compose_worlds() {
+    assert!(check_world(0, 3, "auth.wasm", Some(FAAS_STAGE_WORLD)).is_ok());
+    assert!(check_world(2, 3, "fn.wasm", Some(FAAS_EXEC_WORLD)).is_ok());
+
+    // Only the last part is executed on its own
+    assert!(matches!(check_world(0, 2, "fn.wasm", Some(FAAS_EXEC_WORLD)), Err(AppError::Validation(_))));
+    assert!(matches!(check_world(1, 2, "auth.wasm", Some(FAAS_STAGE_WORLD)), Err(AppError::Validation(_))));
+    assert!(check_world(1, 2, "other.wasm", None).is_err());
+}
+
+#[test]
+fn test_compose_invalid() {
+    assert!(matches!(compose(&[]), Err(AppError::BadRequest(_))));
+    assert!(matches!(
+        compose(&[b"not a component".to_vec(), b"\0asm".to_vec()]),
+        Err(AppError::Validation(_))
+    ));
+}
@@ -0,0 +1,45 @@
+#![allow(unused_imports)]
+use crate::tools::config::{redact_text, redact_value, validate_name};
+use crate::tools::secrets::Secrets;
+use serde_json::json;
+
+#[test]
+fn test_seal_and_open() {
+    let secrets = Secrets::with_key(&[7u8; 32]);
+    let sealed = secrets.seal("faas-modules/s3_faas.wasm#S3_SECRET_KEY", "rustfsadmin").unwrap();
+    assert!(!sealed.ciphertext.contains(&hex::encode("rustfsadmin")));
+
+    let value = secrets.open("faas-modules/s3_faas.wasm#S3_SECRET_KEY", &sealed).unwrap();
+    assert_eq!(value, "rustfsadmin");
+
+    // Bound to the function and name it was set for
+    assert!(secrets.open("faas-modules/other.wasm#S3_SECRET_KEY", &sealed).is_err());
+    assert!(Secrets::with_key(&[8u8; 32]).open("faas-modules/s3_faas.wasm#S3_SECRET_KEY", &sealed).is_err());
+}
+
+#[test]
+fn test_redact() {
+    let secrets = vec!["s3cr3t-t0ken".to_string()];
+    let mut output = json!({ "message": "token is s3cr3t-t0ken", "items": ["s3cr3t-t0ken", 1] });
+    redact_value(&secrets, &mut output);
+    assert_eq!(output, json!({ "message": "token is ***", "items": ["***", 1] }));
+
+    // Short secrets are only replaced as whole strings
+    let secrets = vec!["ab".to_string()];
+    let mut text = "about a tab".to_string();
+    redact_text(&secrets, &mut text);
+    assert_eq!(text, "about a tab");
+    let mut text = "ab".to_string();
+    redact_text(&secrets, &mut text);
+    assert_eq!(text, "***");
+}
+
+#[test]
+fn test_config_names() {
+    assert!(validate_name("S3_ACCESS_KEY").is_ok());
+    assert!(validate_name("_PRIVATE").is_ok());
+    assert!(validate_name("s3_key").is_err());
+    assert!(validate_name("1KEY").is_err());
+    assert!(validate_name("KEY=VALUE").is_err());
+    assert!(validate_name("").is_err());
+}
@@ -0,0 +1,31 @@
+#![allow(unused_imports)]
+use crate::error::AppError;
+use crate::tools::invoke::{split_target, CallLimits};
+
+#[test]
+fn test_call_target() {
+    assert_eq!(split_target("faas-modules/hello.wasm").unwrap(), ("faas-modules", "hello.wasm"));
+    assert_eq!(split_target("faas-modules/team/fn.wasm@prod").unwrap(), ("faas-modules", "team/fn.wasm@prod"));
+    for name in ["hello.wasm", "/hello.wasm", "faas-modules/", ""] {
+        assert!(matches!(split_target(name), Err(AppError::BadRequest(_))), "{}", name);
+    }
+}
+
+#[test]
+fn test_call_limits() {
+    let root = CallLimits::new(1, 3).caller("faas-modules", &["shared".to_string()]);
+
+    // Own bucket and the allowed ones only
+    assert!(root.admit("faas-modules").is_ok());
+    assert!(root.admit("shared").is_ok());
+    assert!(matches!(root.admit("other"), Err(AppError::Forbidden(_))));
+
+    // A callee at the maximum depth can't call any further
+    let child = root.admit("faas-modules").unwrap();
+    let grandchild = child.admit("faas-modules");
+    assert!(matches!(grandchild, Err(AppError::Forbidden(message)) if message.contains("depth")));
+
+    // The budget of 3 is shared by the whole tree and used up by now
+    let exhausted = root.admit("faas-modules");
+    assert!(matches!(exhausted, Err(AppError::Forbidden(message)) if message.contains("budget")));
+}
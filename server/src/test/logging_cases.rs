@@ -0,0 +1,16 @@
+#![allow(unused_imports)]
+use crate::tools::logging::LogLevel;
+use crate::tools::registry::Limits;
+
+#[test]
+fn test_log_level_limits() {
+    let limits: Limits =
+        serde_json::from_str(r#"{"timeout_ms": 1000, "log_level": "warn", "logs_per_second": 10}"#).unwrap();
+    assert_eq!(limits.log_level, Some(LogLevel::Warn));
+    assert_eq!(limits.logs_per_second, Some(10));
+    assert!(LogLevel::Error > LogLevel::Warn && LogLevel::Debug < LogLevel::Info);
+
+    // Unset fields are not written back
+    let limits: Limits = serde_json::from_str(r#"{"timeout_ms": 1000}"#).unwrap();
+    assert_eq!(serde_json::to_string(&limits).unwrap(), r#"{"timeout_ms":1000,"memory_mb":null}"#);
+}
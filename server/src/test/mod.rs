@@ -1 +1,15 @@
-mod test_cases;
\ No newline at end of file
+mod test_cases;
+mod alias_cases;
+mod validate_cases;
+mod s3_cases;
+mod names_cases;
+mod webhook_cases;
+mod scheduler_cases;
+mod triggers_cases;
+mod workflow_cases;
+mod config_cases;
+mod logging_cases;
+mod pipeline_cases;
+mod compose_cases;
+mod invoke_cases;
+mod adapt_cases;
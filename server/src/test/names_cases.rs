@@ -0,0 +1,48 @@
+#![allow(unused_imports)]
+use crate::tools::jobs::system_bucket;
+use crate::tools::names::{validate_bucket, validate_key, FileRoute};
+
+#[test]
+fn test_validate_bucket() {
+    assert!(validate_bucket("faas-modules").is_ok());
+    assert!(validate_bucket("team.functions").is_ok());
+    assert!(validate_bucket("ab").is_err());
+    assert!(validate_bucket("Faas").is_err());
+    assert!(validate_bucket("-faas").is_err());
+    assert!(validate_bucket("faas..modules").is_err());
+    assert!(validate_bucket("192.168.0.1").is_err());
+    assert!(validate_bucket(&system_bucket()).is_err());
+}
+
+#[test]
+fn test_validate_key() {
+    assert!(validate_key("team/service/fn.wasm").is_ok());
+    assert!(validate_key("").is_err());
+    assert!(validate_key("/fn.wasm").is_err());
+    assert!(validate_key("team//fn.wasm").is_err());
+    assert!(validate_key("team/../fn.wasm").is_err());
+    assert!(validate_key("fn.wasm@prod").is_err());
+    assert!(validate_key(".faas/meta/fn.wasm.json").is_err());
+    assert!(validate_key("team/versions/fn.wasm").is_err());
+}
+
+#[test]
+fn test_file_route() {
+    assert_eq!(
+        FileRoute::parse("team/fn.wasm").unwrap(),
+        FileRoute::Object("team/fn.wasm".to_string())
+    );
+    assert_eq!(
+        FileRoute::parse("team/fn.wasm/describe").unwrap(),
+        FileRoute::Describe("team/fn.wasm".to_string())
+    );
+    assert_eq!(
+        FileRoute::parse("team/fn.wasm/versions/abc/restore").unwrap(),
+        FileRoute::Restore("team/fn.wasm".to_string(), "abc".to_string())
+    );
+    assert_eq!(
+        FileRoute::parse("team/fn.wasm/compose").unwrap(),
+        FileRoute::Compose("team/fn.wasm".to_string())
+    );
+    assert!(FileRoute::parse("describe").is_err());
+}
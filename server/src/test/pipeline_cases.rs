@@ -0,0 +1,44 @@
+#![allow(unused_imports)]
+use crate::error::AppError;
+use crate::tools::pipeline::{Pipeline, PipelineRun, StepStatus};
+use serde_json::json;
+
+#[test]
+fn test_pipeline_results() {
+    let pipeline: Pipeline = serde_json::from_value(json!({
+        "name": "test",
+        "steps": [
+            { "name": "first", "bucket": "faas-modules", "key": "a.wasm" },
+            { "bucket": "faas-modules", "key": "b.wasm", "on_error": "continue" },
+            { "bucket": "faas-modules", "key": "c.wasm" },
+            { "bucket": "faas-modules", "key": "d.wasm" }
+        ]
+    }))
+    .unwrap();
+    let steps = &pipeline.steps;
+
+    let mut run = PipelineRun::new(&pipeline);
+    run.record(&steps[0], Ok((Some("v1".to_string()), json!({ "n": 1 }))), 5);
+    run.record(&steps[1], Err(AppError::BadRequest("bad".to_string())), 1);
+    // A failed step that continues keeps the output and the status of the run
+    assert_eq!(run.status, StepStatus::Succeeded);
+    assert_eq!(run.output, Some(json!({ "n": 1 })));
+
+    run.record(&steps[2], Err(AppError::Timeout("slow".to_string())), 2);
+    run.skip(&steps[3]);
+
+    assert_eq!(run.status, StepStatus::Failed);
+    assert_eq!(run.error.as_ref().map(|e| e.status), Some(504));
+    assert_eq!(run.output, Some(json!({ "n": 1 })));
+
+    let statuses: Vec<StepStatus> = run.steps.iter().map(|s| s.status).collect();
+    assert_eq!(
+        statuses,
+        [StepStatus::Succeeded, StepStatus::Failed, StepStatus::Failed, StepStatus::Skipped]
+    );
+    assert_eq!(run.steps[0].name, "first");
+    assert_eq!(run.steps[0].version.as_deref(), Some("v1"));
+    assert_eq!(run.steps[1].name, "b.wasm");
+    assert_eq!(run.steps[1].error.as_ref().map(|e| e.status), Some(400));
+    assert!(run.steps[3].output.is_none() && run.steps[3].error.is_none());
+}
@@ -0,0 +1,18 @@
+#![allow(unused_imports)]
+use crate::tools::s3::{decode_key, encode_key, key_before};
+
+#[test]
+fn test_key_encoding() {
+    assert_eq!(encode_key("team/hello faas.wasm"), "team/hello%20faas.wasm");
+    assert_eq!(decode_key("team/hello%20faas.wasm"), "team/hello faas.wasm");
+    assert_eq!(decode_key(&encode_key("grüße \"x\".wasm")), "grüße \"x\".wasm");
+    assert_eq!(decode_key("100%"), "100%");
+}
+
+#[test]
+fn test_key_before() {
+    assert_eq!(key_before("team/b.wasm"), "team/b.wasl");
+    assert!(key_before("fn-ä.wasm").as_str() < "fn-ä.wasm");
+    assert_eq!(key_before("a"), "`");
+    assert_eq!(key_before("a\0"), "a");
+}
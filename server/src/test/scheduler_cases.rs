@@ -0,0 +1,19 @@
+#![allow(unused_imports)]
+use crate::tools::scheduler::{next_after, parse_cron, parse_timezone};
+
+#[test]
+fn test_cron_next_run() {
+    // 2024-01-01T00:00:00Z, 03:00 in Berlin is 02:00 UTC in winter
+    let cron = parse_cron("0 3 * * *").unwrap();
+    let berlin = parse_timezone("Europe/Berlin").unwrap();
+    assert_eq!(next_after(&cron, berlin, 1704067200), Some(1704074400));
+
+    let utc = parse_timezone("UTC").unwrap();
+    assert_eq!(next_after(&cron, utc, 1704067200), Some(1704078000));
+}
+
+#[test]
+fn test_cron_invalid() {
+    assert!(parse_cron("61 * * * *").is_err());
+    assert!(parse_timezone("Mars/Olympus").is_err());
+}
@@ -63,14 +63,8 @@ fn test_file_valid() -> Result<(), Box<dyn std::error::Error>> {
 async fn test_download_valid() -> Result<(), Box<dyn std::error::Error>> {
     let s3 = S3::new().await;
     let key = "fibonacci_faas.wasm";
-    let wasm_stream = s3.download_file("faas-modules", key).await?;
+    let wasm_bytes = s3.download_bytes("faas-modules", key, None).await?;
 
-    let wasm_bytes = wasm_stream
-        .collect()
-        .await
-        .map_err(|e| AppError::Internal(e.to_string()))?
-        .to_vec();
-    
     let mut config = Config::new();
     config.wasm_component_model(true);
     let engine = Engine::new(&config)?;
@@ -131,19 +125,13 @@ async fn test_exec_remote_valid() -> Result<(), Box<dyn std::error::Error>> {
     let wasm_file = "s3_faas.wasm";
     let input = json!({ "bucket": "faas-modules" });
 
-    let wasm_stream = s3.download_file("faas-modules", wasm_file).await?;
+    let wasm_bytes = s3.download_bytes("faas-modules", wasm_file, None).await?;
 
     let mut config = Config::new();
     config.wasm_component_model(true);
     config.async_support(true);
     let engine = Engine::new(&config)?;
 
-    let wasm_bytes = wasm_stream
-        .collect()
-        .await
-        .map_err(|e| AppError::Internal(e.to_string()))?
-        .to_vec();
-
     let component = Component::from_binary(&engine, &wasm_bytes)?;
 
     let mut linker = Linker::new(&engine);
@@ -0,0 +1,52 @@
+#![allow(unused_imports)]
+use crate::tools::triggers::{diff, EventType, Notification, Snapshot};
+
+#[test]
+fn test_snapshot_diff() {
+    let previous: Snapshot = [
+        ("a.json".to_string(), (1, Some("\"1\"".to_string()))),
+        ("b.json".to_string(), (2, Some("\"2\"".to_string()))),
+        ("c.json".to_string(), (3, Some("\"3\"".to_string()))),
+    ]
+    .into();
+    let current: Snapshot = [
+        ("a.json".to_string(), (1, Some("\"1\"".to_string()))),
+        ("b.json".to_string(), (4, Some("\"4\"".to_string()))),
+        ("d.json".to_string(), (5, Some("\"5\"".to_string()))),
+    ]
+    .into();
+
+    let mut events: Vec<(EventType, String)> = diff("uploads", &previous, &current)
+        .into_iter()
+        .map(|e| (e.event, e.key))
+        .collect();
+    events.sort_by(|a, b| a.1.cmp(&b.1));
+    assert_eq!(
+        events,
+        vec![
+            (EventType::Created, "b.json".to_string()),
+            (EventType::Deleted, "c.json".to_string()),
+            (EventType::Created, "d.json".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_notification_records() {
+    let notification: Notification = serde_json::from_str(
+        r#"{"Records": [
+            {"eventName": "s3:ObjectCreated:Put", "s3": {"bucket": {"name": "uploads"}, "object": {"key": "in/my+file%C3%A4.json", "size": 12, "eTag": "abc"}}},
+            {"eventName": "ObjectRemoved:Delete", "s3": {"bucket": {"name": "uploads"}, "object": {"key": "in/old.json"}}},
+            {"eventName": "s3:ObjectRestore:Completed", "s3": {"bucket": {"name": "uploads"}, "object": {"key": "in/x.json"}}}
+        ]}"#,
+    )
+    .unwrap();
+
+    let events: Vec<_> = notification.records.into_iter().filter_map(|r| r.into_event()).collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].event, EventType::Created);
+    assert_eq!(events[0].key, "in/my fileä.json");
+    assert_eq!(events[0].size, Some(12));
+    assert_eq!(events[1].event, EventType::Deleted);
+    assert_eq!(events[1].key, "in/old.json");
+}
@@ -0,0 +1,15 @@
+#![allow(unused_imports)]
+use crate::tools::validate::Policy;
+
+#[test]
+fn test_policy_allows() {
+    let policy = Policy {
+        max_size: 1024,
+        allowed_imports: vec!["wasi:cli/".to_string(), "wasi:io/".to_string()],
+    };
+
+    assert!(policy.allows("wasi:cli/stdout@0.2.6"));
+    assert!(policy.allows("wasi:io/streams@0.2.6"));
+    assert!(!policy.allows("wasi:sockets/tcp@0.2.6"));
+    assert!(!policy.allows("wasi:clipboard/read"));
+}
@@ -0,0 +1,32 @@
+#![allow(unused_imports)]
+use crate::tools::webhook::{is_public, sign};
+use std::net::IpAddr;
+
+#[test]
+fn test_callback_signature() {
+    assert_eq!(
+        sign("secret", 1700000000, br#"{"ok":true}"#),
+        "sha256=c1afc7c2df3db0690d7d75954610ed1a1d959ce96355ccb8c0a8bc09fd0cfc27"
+    );
+}
+
+#[test]
+fn test_callback_internal_addresses() {
+    for ip in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:127.0.0.1",
+    ] {
+        assert!(!is_public(&ip.parse::<IpAddr>().unwrap()), "{}", ip);
+    }
+    for ip in ["93.184.216.34", "2606:4700::1111"] {
+        assert!(is_public(&ip.parse::<IpAddr>().unwrap()), "{}", ip);
+    }
+}
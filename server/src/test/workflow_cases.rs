@@ -0,0 +1,38 @@
+#![allow(unused_imports)]
+use crate::tools::workflow::{validate_graph, Workflow};
+
+fn workflow(steps: serde_json::Value) -> Workflow {
+    serde_json::from_value(serde_json::json!({ "name": "test", "steps": steps })).unwrap()
+}
+
+#[test]
+fn test_workflow_graph() {
+    let diamond = workflow(serde_json::json!([
+        { "id": "split", "bucket": "faas-modules", "key": "split.wasm" },
+        { "id": "left", "bucket": "faas-modules", "key": "a.wasm", "needs": ["split"], "map": { "parallelism": 4 } },
+        { "id": "right", "bucket": "faas-modules", "key": "b.wasm", "needs": ["split"], "retry": { "attempts": 3 } },
+        { "id": "merge", "bucket": "faas-modules", "key": "merge.wasm", "needs": ["left", "right"] }
+    ]));
+    assert!(validate_graph(&diamond).is_ok());
+}
+
+#[test]
+fn test_workflow_graph_invalid() {
+    let cycle = workflow(serde_json::json!([
+        { "id": "a", "bucket": "faas-modules", "key": "a.wasm", "needs": ["c"] },
+        { "id": "b", "bucket": "faas-modules", "key": "b.wasm", "needs": ["a"] },
+        { "id": "c", "bucket": "faas-modules", "key": "c.wasm", "needs": ["b"] }
+    ]));
+    assert!(validate_graph(&cycle).is_err());
+
+    let unknown = workflow(serde_json::json!([
+        { "id": "a", "bucket": "faas-modules", "key": "a.wasm", "needs": ["missing"] }
+    ]));
+    assert!(validate_graph(&unknown).is_err());
+
+    let duplicate = workflow(serde_json::json!([
+        { "id": "a", "bucket": "faas-modules", "key": "a.wasm" },
+        { "id": "a", "bucket": "faas-modules", "key": "b.wasm" }
+    ]));
+    assert!(validate_graph(&duplicate).is_err());
+}
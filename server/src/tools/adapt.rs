@@ -0,0 +1,35 @@
+use crate::error::AppError;
+use crate::tools::inspect::FAAS_EXEC_WORLD;
+use wasmparser::{Parser, Payload};
+use wit_component::ComponentEncoder;
+
+/// Reactor adapter bundled with the server, turns `wasm32-wasip1` core modules into components
+const REACTOR_ADAPTER: &[u8] = include_bytes!("../../../wasi_snapshot_preview1.reactor.wasm");
+
+const ADAPTER_NAME: &str = "wasi_snapshot_preview1";
+
+/// A core module built with `wit-bindgen` carries its world in a custom section named
+/// `component-type:{generator}:{version}:local:faas:faas-exec:{suffix}`
+pub fn is_adaptable(wasm_bytes: &[u8]) -> bool {
+    if !Parser::is_core_wasm(wasm_bytes) {
+        return false;
+    }
+
+    let world = format!(":{}:", FAAS_EXEC_WORLD.replace('/', ":"));
+    Parser::new(0)
+        .parse_all(wasm_bytes)
+        .flatten()
+        .any(|payload| {
+            matches!(payload, Payload::CustomSection(section)
+                if section.name().starts_with("component-type:") && section.name().contains(&world))
+        })
+}
+
+/// Same as `wasm-tools component new <module> --adapt wasi_snapshot_preview1.reactor.wasm`
+pub fn adapt(wasm_bytes: &[u8]) -> Result<Vec<u8>, AppError> {
+    ComponentEncoder::default()
+        .module(wasm_bytes)
+        .and_then(|encoder| encoder.validate(true).adapter(ADAPTER_NAME, REACTOR_ADAPTER))
+        .and_then(|mut encoder| encoder.encode())
+        .map_err(|e| AppError::Validation(format!("Failed to adapt core module: {:#}", e)))
+}
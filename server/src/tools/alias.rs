@@ -0,0 +1,75 @@
+use crate::error::AppError;
+use crate::tools::s3::{S3, INTERNAL_PREFIX};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Alias that is resolved to the current object when not explicitly defined
+pub const LATEST: &str = "latest";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Canary {
+    pub version: String,
+    /// Share of the traffic (0-100) routed to the canary version
+    pub weight: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Alias {
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canary: Option<Canary>,
+    /// Version the alias pointed at before the last change, used for rollback
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+}
+
+impl Alias {
+    /// Picks the version to execute, `roll` is a value in 0..100
+    pub fn pick(&self, roll: u8) -> &str {
+        match &self.canary {
+            Some(canary) if roll < canary.weight => &canary.version,
+            _ => &self.version,
+        }
+    }
+}
+
+pub type Aliases = BTreeMap<String, Alias>;
+
+/// Splits an addressed key like `hello_faas.wasm@prod` into key and alias
+pub fn split_alias(key: &str) -> (&str, Option<&str>) {
+    match key.rsplit_once('@') {
+        Some((key, alias)) if !key.is_empty() && !alias.is_empty() => (key, Some(alias)),
+        _ => (key, None),
+    }
+}
+
+fn alias_key(key: &str) -> String {
+    format!("{}aliases/{}.json", INTERNAL_PREFIX, key)
+}
+
+pub async fn load(s3: &S3, bucket: &str, key: &str) -> Result<Aliases, AppError> {
+    Ok(s3.get_json(bucket, &alias_key(key)).await?.unwrap_or_default())
+}
+
+pub async fn save(s3: &S3, bucket: &str, key: &str, aliases: &Aliases) -> Result<(), AppError> {
+    s3.put_json(bucket, &alias_key(key), aliases).await
+}
+
+pub async fn remove(s3: &S3, bucket: &str, key: &str) -> Result<(), AppError> {
+    s3.delete_file(bucket, &alias_key(key)).await
+}
+
+/// Resolves an alias to a version id, `None` means the current object
+pub async fn resolve(
+    s3: &S3,
+    bucket: &str,
+    key: &str,
+    alias: &str,
+) -> Result<Option<String>, AppError> {
+    let aliases = load(s3, bucket, key).await?;
+    match aliases.get(alias) {
+        Some(alias) => Ok(Some(alias.pick(rand::random_range(0..100)).to_string())),
+        None if alias == LATEST => Ok(None),
+        None => Err(AppError::NotFound(format!("Alias {} not found for {}", alias, key))),
+    }
+}
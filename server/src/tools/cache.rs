@@ -0,0 +1,281 @@
+use crate::error::AppError;
+use crate::tools::runtime::Runtime;
+use crate::tools::env_or;
+use crate::tools::s3::{GetOptions, S3};
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+use wasmtime::component::Component;
+
+/// Digests remembered for objects without a recorded one, forgotten all at once beyond it
+const MAX_DIGESTS: usize = 4096;
+
+/// Content addressed cache of modules, keyed by their sha256.
+///
+/// Module bytes are streamed from S3 to disk and verified against the digest of
+/// the registry, precompiled artifacts are memory-mapped on the next cold start.
+pub struct ModuleCache {
+    dir: PathBuf,
+    engine_hash: String,
+    compiled: Mutex<Compiled>,
+    /// Digests of objects without a registry entry, keyed by bucket, key and ETag
+    digests: Mutex<HashMap<String, String>>,
+}
+
+/// Compiled components kept in memory, the least recently used one is dropped beyond `capacity`
+struct Compiled {
+    capacity: usize,
+    /// Component and the tick of its last use
+    entries: HashMap<String, (Component, u64)>,
+    tick: u64,
+}
+
+impl Compiled {
+    fn get(&mut self, sha256: &str) -> Option<Component> {
+        self.tick += 1;
+        let (component, used) = self.entries.get_mut(sha256)?;
+        *used = self.tick;
+        Some(component.clone())
+    }
+
+    fn insert(&mut self, sha256: &str, component: &Component) {
+        self.tick += 1;
+        self.entries.insert(sha256.to_string(), (component.clone(), self.tick));
+        while self.entries.len() > self.capacity {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(sha256, _)| sha256.clone())
+            else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+impl ModuleCache {
+    /// Reads `FAAS_CACHE_DIR` and `FAAS_CACHE_COMPILED` (default 64 components)
+    pub fn new(runtime: &Runtime) -> Result<ModuleCache, AppError> {
+        let dir = PathBuf::from(
+            std::env::var("FAAS_CACHE_DIR").unwrap_or_else(|_| ".faas-cache".to_string()),
+        );
+        std::fs::create_dir_all(&dir)?;
+
+        let mut hasher = DefaultHasher::new();
+        runtime.engine.precompile_compatibility_hash().hash(&mut hasher);
+
+        Ok(ModuleCache {
+            dir,
+            engine_hash: format!("{:016x}", hasher.finish()),
+            compiled: Mutex::new(Compiled {
+                capacity: env_or("FAAS_CACHE_COMPILED", 64).max(1) as usize,
+                entries: HashMap::new(),
+                tick: 0,
+            }),
+            digests: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn module_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(format!("{}.wasm", sha256))
+    }
+
+    fn artifact_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(format!("{}-{}.cwasm", sha256, self.engine_hash))
+    }
+
+    /// Returns the compiled component of an object, `sha256` is the digest recorded on upload.
+    /// Objects without one cost a HEAD request instead of a download on every call
+    pub async fn load(
+        &self,
+        s3: &S3,
+        runtime: &Runtime,
+        bucket: &str,
+        key: &str,
+        version: Option<&str>,
+        sha256: Option<&str>,
+    ) -> Result<Component, AppError> {
+        let mut version = version.map(str::to_string);
+        let mut identity = None;
+        let known = match sha256 {
+            Some(sha256) => Some(sha256.to_string()),
+            // Without a recorded digest the object is recognized by its ETag
+            None => {
+                let options = GetOptions {
+                    version: version.as_deref(),
+                    ..GetOptions::default()
+                };
+                let info = s3
+                    .head_object(bucket, key, &options)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound(format!("File {} not found in {}", key, bucket)))?;
+                // The download is pinned to the version looked at, where the bucket has versions
+                version = info.version.filter(|v| v != "null").or(version);
+                identity = info.etag.map(|etag| format!("{}/{}#{}", bucket, key, etag));
+                identity
+                    .as_ref()
+                    .and_then(|identity| self.digests.lock().unwrap().get(identity).cloned())
+            }
+        };
+
+        if let Some(sha256) = &known {
+            let compiled = self.compiled.lock().unwrap().get(sha256);
+            if let Some(component) = compiled {
+                return Ok(component);
+            }
+            if let Some(component) = self.load_artifact(runtime, sha256).await {
+                self.remember(sha256, &component);
+                return Ok(component);
+            }
+        }
+
+        let sha256 = match known {
+            Some(known) if self.verify_module(&known).await? => known,
+            _ => self.download(s3, bucket, key, version.as_deref(), sha256).await?,
+        };
+        if let Some(identity) = identity {
+            let mut digests = self.digests.lock().unwrap();
+            if digests.len() >= MAX_DIGESTS {
+                digests.clear();
+            }
+            digests.insert(identity, sha256.clone());
+        }
+
+        // Compiling takes long enough to stall the other tasks of the worker thread
+        let engine = runtime.engine.clone();
+        let path = self.module_path(&sha256);
+        let component = tokio::task::spawn_blocking(move || Component::from_file(&engine, path))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))??;
+        if let Err(e) = self.store_artifact(&component, &sha256).await {
+            tracing::warn!("Failed to cache precompiled {}: {}", key, e);
+        }
+        self.remember(&sha256, &component);
+
+        Ok(component)
+    }
+
+    /// Drops the compiled components and module bytes of the given digests
+    pub async fn evict(&self, sha256s: &[String]) {
+        {
+            let mut compiled = self.compiled.lock().unwrap();
+            for sha256 in sha256s {
+                compiled.entries.remove(sha256);
+            }
+        }
+
+        for sha256 in sha256s {
+            tokio::fs::remove_file(self.module_path(sha256)).await.ok();
+            tokio::fs::remove_file(self.artifact_path(sha256)).await.ok();
+        }
+    }
+
+    fn remember(&self, sha256: &str, component: &Component) {
+        self.compiled.lock().unwrap().insert(sha256, component);
+    }
+
+    async fn load_artifact(&self, runtime: &Runtime, sha256: &str) -> Option<Component> {
+        let path = self.artifact_path(sha256);
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return None;
+        }
+
+        // SAFETY: artifacts are only written by `store_artifact` from components compiled
+        // with this engine, the cache directory must not be writable by anyone else
+        let engine = runtime.engine.clone();
+        let artifact = path.clone();
+        let deserialized =
+            tokio::task::spawn_blocking(move || unsafe { Component::deserialize_file(&engine, &artifact) }).await;
+        match deserialized.map_err(|e| AppError::Internal(e.to_string())).and_then(|r| r.map_err(AppError::from)) {
+            Ok(component) => Some(component),
+            Err(e) => {
+                tracing::warn!("Dropping unusable artifact {}: {}", path.display(), e);
+                tokio::fs::remove_file(&path).await.ok();
+                None
+            }
+        }
+    }
+
+    async fn store_artifact(&self, component: &Component, sha256: &str) -> Result<(), AppError> {
+        let component = component.clone();
+        let bytes = tokio::task::spawn_blocking(move || component.serialize())
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))??;
+        write_atomic(&self.dir, &self.artifact_path(sha256), &bytes).await
+    }
+
+    /// Checks the module bytes on disk, a corrupted file is removed
+    async fn verify_module(&self, sha256: &str) -> Result<bool, AppError> {
+        let path = self.module_path(sha256);
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+
+        if format!("{:x}", Sha256::digest(&bytes)) == sha256 {
+            return Ok(true);
+        }
+
+        tracing::warn!("Dropping corrupted module {}", path.display());
+        tokio::fs::remove_file(&path).await?;
+        Ok(false)
+    }
+
+    /// Streams the object to disk while hashing it, returns the digest of the bytes
+    async fn download(
+        &self,
+        s3: &S3,
+        bucket: &str,
+        key: &str,
+        version: Option<&str>,
+        expected: Option<&str>,
+    ) -> Result<String, AppError> {
+        let tmp = self.dir.join(format!("{:016x}.part", rand::random::<u64>()));
+
+        let result = async {
+            let mut stream = s3.download_file_version(bucket, key, version).await?;
+            let mut file = tokio::fs::File::create(&tmp).await?;
+            let mut hasher = Sha256::new();
+
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|e| AppError::Internal(e.to_string()))?;
+                hasher.update(&chunk);
+                file.write_all(&chunk).await?;
+            }
+            file.sync_all().await?;
+
+            let sha256 = format!("{:x}", hasher.finalize());
+            if let Some(expected) = expected
+                && expected != sha256
+            {
+                return Err(AppError::Forbidden(format!(
+                    "Checksum mismatch for {}: expected {}, got {}",
+                    key, expected, sha256
+                )));
+            }
+
+            tokio::fs::rename(&tmp, self.module_path(&sha256)).await?;
+            Ok(sha256)
+        }
+        .await;
+
+        if result.is_err() {
+            tokio::fs::remove_file(&tmp).await.ok();
+        }
+        result
+    }
+}
+
+async fn write_atomic(dir: &Path, path: &Path, bytes: &[u8]) -> Result<(), AppError> {
+    let tmp = dir.join(format!("{:016x}.part", rand::random::<u64>()));
+    tokio::fs::write(&tmp, bytes).await?;
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
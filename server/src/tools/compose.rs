@@ -0,0 +1,56 @@
+use crate::error::AppError;
+use crate::tools::inspect::{FAAS_EXEC_WORLD, FAAS_STAGE_WORLD, NEXT_IMPORT};
+use wac_graph::types::Package;
+use wac_graph::{CompositionGraph, EncodeOptions};
+
+/// Name of the function every part exports
+const EXEC_EXPORT: &str = "exec";
+
+/// Upper bound of parts, stages followed by the function they wrap
+pub const MAX_PARTS: usize = 16;
+
+/// Every part but the last is a `faas-stage`, the last one is the `faas-exec` function
+pub fn check_world(index: usize, count: usize, key: &str, world: Option<&str>) -> Result<(), AppError> {
+    let expected = match index + 1 == count {
+        true => FAAS_EXEC_WORLD,
+        false => FAAS_STAGE_WORLD,
+    };
+    if world != Some(expected) {
+        return Err(AppError::Validation(format!(
+            "Part {} ({}) has to be a {} component",
+            index, key, expected
+        )));
+    }
+    Ok(())
+}
+
+/// Links `parts` into one component, the `next` import of every stage is satisfied by the
+/// `exec` export of the part after it. Imports left open (WASI) are merged into the
+/// imports of the composed component.
+pub fn compose(parts: &[Vec<u8>]) -> Result<Vec<u8>, AppError> {
+    let failed = |e: &dyn std::fmt::Display| AppError::Validation(format!("Composition failed: {}", e));
+
+    let mut graph = CompositionGraph::new();
+    let mut next = None;
+    for (index, bytes) in parts.iter().enumerate().rev() {
+        let name = format!("faas:part{}", index);
+        let package = Package::from_bytes(&name, None, bytes.as_slice(), graph.types_mut())
+            .map_err(|e| failed(&e))?;
+        let package = graph.register_package(package).map_err(|e| failed(&e))?;
+        let instance = graph.instantiate(package);
+        if let Some(next) = next {
+            graph
+                .set_instantiation_argument(instance, NEXT_IMPORT, next)
+                .map_err(|e| failed(&e))?;
+        }
+        next = Some(
+            graph
+                .alias_instance_export(instance, EXEC_EXPORT)
+                .map_err(|e| failed(&e))?,
+        );
+    }
+
+    let exec = next.ok_or_else(|| AppError::BadRequest("Nothing to compose".to_string()))?;
+    graph.export(exec, EXEC_EXPORT).map_err(|e| failed(&e))?;
+    graph.encode(EncodeOptions::default()).map_err(|e| failed(&e))
+}
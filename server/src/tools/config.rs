@@ -0,0 +1,223 @@
+use crate::error::AppError;
+use crate::tools::s3::{S3, INTERNAL_PREFIX};
+use crate::tools::secrets::{Sealed, Secrets};
+use crate::tools::unix_now;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const REDACTED: &str = "***";
+
+/// Shorter secrets are only redacted where they make up a whole string, replacing every
+/// occurrence of a few characters would garble unrelated text
+const MIN_REDACTED_SUBSTRING: usize = 8;
+
+/// Entries per function, the environment of a guest should stay small
+const MAX_ENTRIES: usize = 100;
+
+const MAX_VALUE_SIZE: usize = 32 * 1024;
+
+/// Configuration of a function, stored next to its registry entry
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FunctionConfig {
+    #[serde(default)]
+    pub config: BTreeMap<String, String>,
+    #[serde(default)]
+    pub secrets: BTreeMap<String, Sealed>,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+/// Configuration as returned by the API, secret values are never sent back
+#[derive(Serialize, Debug)]
+pub struct ConfigView {
+    pub config: BTreeMap<String, String>,
+    pub secrets: BTreeMap<String, String>,
+    pub updated_at: u64,
+}
+
+impl FunctionConfig {
+    pub fn redacted(&self) -> ConfigView {
+        ConfigView {
+            config: self.config.clone(),
+            secrets: self
+                .secrets
+                .keys()
+                .map(|name| (name.clone(), REDACTED.to_string()))
+                .collect(),
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// Plain environment of an execution, with the secret values to redact from its output
+pub struct Environment {
+    pub vars: Vec<(String, String)>,
+    pub secrets: Vec<String>,
+}
+
+impl Environment {
+    /// Guest traps and messages are logged and recorded like outputs
+    pub fn redact_error(&self, error: AppError) -> AppError {
+        if self.secrets.is_empty() {
+            return error;
+        }
+        match error {
+            AppError::WasmtimeError(err) => {
+                let mut message = format!("{:#}", err);
+                redact_text(&self.secrets, &mut message);
+                AppError::WasmtimeError(wasmtime::Error::msg(message))
+            }
+            AppError::Internal(mut message) => {
+                redact_text(&self.secrets, &mut message);
+                AppError::Internal(message)
+            }
+            other => other,
+        }
+    }
+}
+
+/// Replaces secret values in the strings of `value`
+pub fn redact_value(secrets: &[String], value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => redact_text(secrets, text),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| redact_value(secrets, item)),
+        serde_json::Value::Object(map) => map.values_mut().for_each(|item| redact_value(secrets, item)),
+        _ => {}
+    }
+}
+
+/// Replaces every secret value in `text`
+pub fn redact_text(secrets: &[String], text: &mut String) {
+    for secret in secrets.iter().filter(|s| !s.is_empty()) {
+        if secret.len() < MIN_REDACTED_SUBSTRING {
+            if text == secret {
+                *text = REDACTED.to_string();
+            }
+        } else if text.contains(secret.as_str()) {
+            *text = text.replace(secret.as_str(), REDACTED);
+        }
+    }
+}
+
+fn config_key(name: &str) -> String {
+    format!("{}config/{}.json", INTERNAL_PREFIX, name)
+}
+
+/// Authenticated data of a secret, ties the ciphertext to the function and name
+fn secret_context(namespace: &str, name: &str, secret: &str) -> String {
+    format!("{}/{}#{}", namespace, name, secret)
+}
+
+pub async fn load(s3: &S3, namespace: &str, name: &str) -> Result<FunctionConfig, AppError> {
+    Ok(s3
+        .get_json(namespace, &config_key(name))
+        .await?
+        .unwrap_or_default())
+}
+
+pub async fn remove(s3: &S3, namespace: &str, name: &str) -> Result<(), AppError> {
+    s3.delete_file(namespace, &config_key(name)).await
+}
+
+/// Sets the given entries, `None` removes one, other entries are kept
+pub async fn update(
+    s3: &S3,
+    secrets: &Secrets,
+    namespace: &str,
+    name: &str,
+    config: BTreeMap<String, Option<String>>,
+    secret_values: BTreeMap<String, Option<String>>,
+) -> Result<FunctionConfig, AppError> {
+    let mut stored = load(s3, namespace, name).await?;
+
+    for (key, value) in config {
+        validate_name(&key)?;
+        match value {
+            Some(value) => {
+                stored.config.insert(key, checked(value)?);
+            }
+            None => {
+                stored.config.remove(&key);
+            }
+        }
+    }
+    for (key, value) in secret_values {
+        validate_name(&key)?;
+        match value {
+            Some(value) => {
+                let sealed = secrets.seal(&secret_context(namespace, name, &key), &checked(value)?)?;
+                stored.secrets.insert(key, sealed);
+            }
+            None => {
+                stored.secrets.remove(&key);
+            }
+        }
+    }
+
+    if let Some(key) = stored.config.keys().find(|key| stored.secrets.contains_key(*key)) {
+        return Err(AppError::BadRequest(format!(
+            "{} is defined as config and as secret",
+            key
+        )));
+    }
+    if stored.config.len() + stored.secrets.len() > MAX_ENTRIES {
+        return Err(AppError::BadRequest(format!(
+            "A function can have at most {} config entries and secrets",
+            MAX_ENTRIES
+        )));
+    }
+
+    stored.updated_at = unix_now();
+    s3.put_json(namespace, &config_key(name), &stored).await?;
+    Ok(stored)
+}
+
+/// Decrypted environment of an execution, read on every call so updates apply without a redeploy
+pub async fn environment(
+    s3: &S3,
+    secrets: &Secrets,
+    namespace: &str,
+    name: &str,
+) -> Result<Environment, AppError> {
+    let stored = load(s3, namespace, name).await?;
+
+    let mut environment = Environment {
+        vars: stored.config.into_iter().collect(),
+        secrets: Vec::with_capacity(stored.secrets.len()),
+    };
+    for (key, sealed) in &stored.secrets {
+        let value = secrets.open(&secret_context(namespace, name, key), sealed)?;
+        environment.vars.push((key.clone(), value.clone()));
+        environment.secrets.push(value);
+    }
+    Ok(environment)
+}
+
+/// Names become environment variables, `[A-Z_][A-Z0-9_]*`
+pub fn validate_name(name: &str) -> Result<(), AppError> {
+    let valid = name.len() <= 128
+        && name
+            .bytes()
+            .next()
+            .is_some_and(|b| b.is_ascii_uppercase() || b == b'_')
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_');
+    if !valid {
+        return Err(AppError::BadRequest(format!(
+            "Invalid config name {}, use uppercase letters, digits and _",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn checked(value: String) -> Result<String, AppError> {
+    if value.len() > MAX_VALUE_SIZE || value.contains('\0') {
+        return Err(AppError::BadRequest(format!(
+            "Config values are limited to {} bytes without NUL",
+            MAX_VALUE_SIZE
+        )));
+    }
+    Ok(value)
+}
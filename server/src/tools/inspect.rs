@@ -0,0 +1,138 @@
+use crate::error::AppError;
+use crate::tools::runtime::Runtime;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use wasmparser::{KnownCustom, Parser, Payload};
+use wasmtime::component::types::ComponentItem;
+use wasmtime::Engine;
+
+/// World the server binds with `bindgen!`
+pub const FAAS_EXEC_WORLD: &str = "local:faas/faas-exec";
+
+/// World of components that wrap another function, they can only be executed once composed
+pub const FAAS_STAGE_WORLD: &str = "local:faas/faas-stage";
+
+/// Import of a stage that is satisfied by the `exec` export of the next part
+pub const NEXT_IMPORT: &str = "next";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ComponentInfo {
+    pub world: Option<String>,
+    pub imports: Vec<String>,
+    pub exports: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ItemDescription {
+    pub name: String,
+    pub kind: String,
+    /// Names exported by an instance item
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ComponentDescription {
+    pub world: Option<String>,
+    pub compatible: bool,
+    pub incompatibility: Option<String>,
+    pub imports: Vec<ItemDescription>,
+    pub exports: Vec<ItemDescription>,
+    pub producers: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ComponentDescription {
+    pub fn info(&self) -> ComponentInfo {
+        ComponentInfo {
+            world: self.world.clone(),
+            imports: self.imports.iter().map(|i| i.name.clone()).collect(),
+            exports: self.exports.iter().map(|e| e.name.clone()).collect(),
+        }
+    }
+}
+
+fn describe_item(engine: &Engine, name: &str, item: ComponentItem) -> ItemDescription {
+    let (kind, items) = match item {
+        ComponentItem::ComponentFunc(_) => ("func", vec![]),
+        ComponentItem::CoreFunc(_) => ("core-func", vec![]),
+        ComponentItem::Module(_) => ("module", vec![]),
+        ComponentItem::Component(_) => ("component", vec![]),
+        ComponentItem::ComponentInstance(instance) => (
+            "instance",
+            instance
+                .exports(engine)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        ),
+        ComponentItem::Type(_) => ("type", vec![]),
+        ComponentItem::Resource(_) => ("resource", vec![]),
+    };
+
+    ItemDescription {
+        name: name.to_string(),
+        kind: kind.to_string(),
+        items,
+    }
+}
+
+/// Collects the producers sections of the component and all nested modules
+fn producers(wasm_bytes: &[u8]) -> BTreeMap<String, BTreeSet<String>> {
+    let mut producers: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for payload in Parser::new(0).parse_all(wasm_bytes).flatten() {
+        let Payload::CustomSection(section) = payload else {
+            continue;
+        };
+        let KnownCustom::Producers(reader) = section.as_known() else {
+            continue;
+        };
+        for field in reader.into_iter().flatten() {
+            let values = producers.entry(field.name.to_string()).or_default();
+            for value in field.values.into_iter().flatten() {
+                values.insert(format!("{} {}", value.name, value.version).trim().to_string());
+            }
+        }
+    }
+
+    producers
+}
+
+pub fn describe(runtime: &Runtime, wasm_bytes: &[u8]) -> Result<ComponentDescription, AppError> {
+    if !Parser::is_component(wasm_bytes) {
+        return Err(AppError::BadRequest(
+            "Not a WebAssembly component, core modules need to be adapted first".to_string(),
+        ));
+    }
+
+    let component = runtime.compile(wasm_bytes)?;
+    let ty = component.component_type();
+
+    let imports = ty
+        .imports(&runtime.engine)
+        .map(|(name, item)| describe_item(&runtime.engine, name, item))
+        .collect();
+    let exports = ty
+        .exports(&runtime.engine)
+        .map(|(name, item)| describe_item(&runtime.engine, name, item))
+        .collect();
+
+    let incompatibility = runtime.prepare(&component).err().map(|e| e.to_string());
+    let compatible = incompatibility.is_none();
+    let world = if compatible {
+        Some(FAAS_EXEC_WORLD.to_string())
+    } else {
+        runtime
+            .prepare_stage(&component)
+            .ok()
+            .map(|_| FAAS_STAGE_WORLD.to_string())
+    };
+
+    Ok(ComponentDescription {
+        world,
+        compatible,
+        incompatibility,
+        imports,
+        exports,
+        producers: producers(wasm_bytes),
+    })
+}
@@ -0,0 +1,286 @@
+use crate::error::AppError;
+use crate::tools::cache::ModuleCache;
+use crate::tools::logging::{GuestLogger, LogRates};
+use crate::tools::runtime::Runtime;
+use crate::tools::s3::S3;
+use crate::tools::secrets::Secrets;
+use crate::tools::signing::Verifier;
+use crate::tools::{alias, config, env_or, inspect, names, registry};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+
+/// Result of a function execution
+#[derive(Serialize, Debug, Clone)]
+pub struct Invocation {
+    /// Version that was executed, `None` for the current object of an unversioned bucket
+    pub version: Option<String>,
+    pub output: serde_json::Value,
+    /// Secret values of the function, replaced where the output is recorded
+    #[serde(skip)]
+    pub secrets: Vec<String>,
+}
+
+impl Invocation {
+    /// Output for job records and run histories, the caller gets `output` unchanged
+    pub fn recorded_output(&self) -> serde_json::Value {
+        let mut output = self.output.clone();
+        config::redact_value(&self.secrets, &mut output);
+        output
+    }
+}
+
+/// Everything needed to execute a stored function, shared by the sync and async paths
+pub struct Invoker {
+    pub s3: Arc<S3>,
+    pub runtime: Arc<Runtime>,
+    pub cache: Arc<ModuleCache>,
+    pub verifier: Arc<Verifier>,
+    pub secrets: Arc<Secrets>,
+    /// Nesting of `local:faas/invoke` calls, the outermost function has depth 0
+    pub max_depth: u32,
+    /// `local:faas/invoke` calls of a whole call tree
+    pub call_budget: u32,
+    /// Guest log rate limits, per function
+    log_rates: Arc<LogRates>,
+}
+
+/// Limits of the `local:faas/invoke` calls of a function, the callee inherits them
+#[derive(Clone)]
+pub struct CallLimits {
+    depth: u32,
+    max_depth: u32,
+    /// Calls left, shared by every function of the tree
+    budget: Arc<AtomicU32>,
+    call_budget: u32,
+    /// Bucket of the calling function, its callees have to be in it or in `allowed`
+    bucket: String,
+    allowed: Vec<String>,
+}
+
+impl CallLimits {
+    /// Limits of the outermost function of a call tree
+    pub fn new(max_depth: u32, call_budget: u32) -> CallLimits {
+        CallLimits {
+            depth: 0,
+            max_depth,
+            budget: Arc::new(AtomicU32::new(call_budget)),
+            call_budget,
+            bucket: String::new(),
+            allowed: Vec::new(),
+        }
+    }
+
+    /// Limits of a function of `bucket` that may also call the functions of `allowed`
+    pub fn caller(self, bucket: &str, allowed: &[String]) -> CallLimits {
+        CallLimits {
+            bucket: bucket.to_string(),
+            allowed: allowed.to_vec(),
+            ..self
+        }
+    }
+
+    /// Takes a call of a function of `bucket` from the budget, returns the limits of the callee
+    pub fn admit(&self, bucket: &str) -> Result<CallLimits, AppError> {
+        if self.depth >= self.max_depth {
+            return Err(AppError::Forbidden(format!(
+                "Call depth limit of {} reached",
+                self.max_depth
+            )));
+        }
+        if bucket != self.bucket && !self.allowed.iter().any(|allowed| allowed == bucket) {
+            return Err(AppError::Forbidden(format!(
+                "Functions of {} may not call functions of {}",
+                self.bucket, bucket
+            )));
+        }
+        if self
+            .budget
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1))
+            .is_err()
+        {
+            return Err(AppError::Forbidden(format!(
+                "Call budget of {} exhausted",
+                self.call_budget
+            )));
+        }
+
+        Ok(CallLimits {
+            depth: self.depth + 1,
+            ..self.clone()
+        })
+    }
+}
+
+/// Caller of a function, guest calls through `local:faas/invoke` inherit its limits
+#[derive(Clone)]
+pub struct CallContext {
+    invoker: Arc<Invoker>,
+    limits: CallLimits,
+    /// Deadline of the calling function, callees never run past it
+    deadline: Option<Instant>,
+    span: tracing::Span,
+}
+
+impl CallContext {
+    /// Runs `{bucket}/{key}` for a guest, input and output are JSON strings
+    pub async fn call(&self, name: &str, input: &str) -> Result<String, AppError> {
+        let (bucket, key) = split_target(name)?;
+        let callee = CallContext {
+            limits: self.limits.admit(bucket)?,
+            ..self.clone()
+        };
+        let input: serde_json::Value = serde_json::from_str(input)
+            .map_err(|e| AppError::BadRequest(format!("Input of {} is not JSON: {}", name, e)))?;
+
+        // Boxed, the callee may call again
+        let invocation = Box::pin(self.invoker.invoke_in(bucket, key, None, &input, callee)).await?;
+        serde_json::to_string(&invocation.output).map_err(|e| AppError::Internal(e.to_string()))
+    }
+}
+
+impl Invoker {
+    /// Reads `FAAS_INVOKE_MAX_DEPTH` and `FAAS_INVOKE_BUDGET`
+    pub fn new(
+        s3: Arc<S3>,
+        runtime: Arc<Runtime>,
+        cache: Arc<ModuleCache>,
+        verifier: Arc<Verifier>,
+        secrets: Arc<Secrets>,
+    ) -> Invoker {
+        Invoker {
+            s3,
+            runtime,
+            cache,
+            verifier,
+            secrets,
+            max_depth: env_or("FAAS_INVOKE_MAX_DEPTH", 4) as u32,
+            call_budget: env_or("FAAS_INVOKE_BUDGET", 32) as u32,
+            log_rates: Arc::new(LogRates::default()),
+        }
+    }
+
+    /// Runs `key` (or `key@alias`, resolved on every call) of `bucket`, an explicit `version`
+    /// takes precedence over the alias
+    pub async fn invoke(
+        self: &Arc<Self>,
+        bucket: &str,
+        key: &str,
+        version: Option<String>,
+        input: &serde_json::Value,
+    ) -> Result<Invocation, AppError> {
+        let context = CallContext {
+            invoker: self.clone(),
+            limits: CallLimits::new(self.max_depth, self.call_budget),
+            deadline: None,
+            span: tracing::Span::current(),
+        };
+        self.invoke_in(bucket, key, version, input, context).await
+    }
+
+    /// Runs the function as callee of `context`, in a child span of the caller
+    async fn invoke_in(
+        &self,
+        bucket: &str,
+        key: &str,
+        version: Option<String>,
+        input: &serde_json::Value,
+        mut context: CallContext,
+    ) -> Result<Invocation, AppError> {
+        let invocation = format!("{:032x}", rand::random::<u128>());
+        let span = tracing::info_span!(
+            parent: &context.span,
+            "invoke",
+            bucket,
+            key,
+            depth = context.limits.depth,
+            invocation = %invocation
+        );
+
+        async move {
+            let (key, alias) = alias::split_alias(key);
+            names::validate_object(bucket, key)?;
+
+            let version = match (version, alias) {
+                (Some(version), _) => Some(version),
+                (None, Some(alias)) => alias::resolve(&self.s3, bucket, key, alias).await?,
+                (None, None) => None,
+            };
+
+            let meta = registry::load(&self.s3, bucket, key).await?;
+            if meta.as_ref().and_then(|m| m.world.as_deref()) == Some(inspect::FAAS_STAGE_WORLD) {
+                return Err(AppError::BadRequest(format!(
+                    "{} is a stage, compose it with a function to execute it",
+                    key
+                )));
+            }
+            let mut limits = meta.as_ref().map(|m| m.limits.clone()).unwrap_or_default();
+            context.limits = context.limits.caller(bucket, &limits.call_buckets);
+            let recorded = meta.as_ref().and_then(|m| m.version_for(version.as_deref()));
+
+            // Signature first, the cache refuses bytes that do not match the recorded digest
+            self.verifier.verify_function(key, recorded)?;
+            let sha256 = recorded.map(|v| v.sha256.as_str());
+
+            let component = self
+                .cache
+                .load(&self.s3, &self.runtime, bucket, key, version.as_deref(), sha256)
+                .await?;
+
+            // A callee gets what is left of the caller's time, at most its own timeout
+            if let Some(deadline) = context.deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now()).as_millis() as u64;
+                if remaining == 0 {
+                    return Err(AppError::Timeout("Deadline of the calling function exceeded".to_string()));
+                }
+                limits.timeout_ms = Some(limits.timeout_ms.map_or(remaining, |t| t.min(remaining)));
+            }
+            context.deadline = limits
+                .timeout_ms
+                .map(|ms| Instant::now() + Duration::from_millis(ms));
+            context.span = tracing::Span::current();
+
+            let input_json =
+                serde_json::to_string(input).map_err(|e| AppError::Internal(e.to_string()))?;
+
+            // Read on every call, config and secrets change without a redeploy
+            let environment = config::environment(&self.s3, &self.secrets, bucket, key).await?;
+
+            let logger = GuestLogger::new(
+                format!("{}/{}", bucket, key),
+                version.clone(),
+                invocation,
+                limits.log_level,
+                limits.logs_per_second,
+                environment.secrets.clone(),
+                self.log_rates.clone(),
+            );
+
+            let output_json_str = self
+                .runtime
+                .call(&component, &input_json, &limits, &environment.vars, context, logger)
+                .await
+                .map_err(|e| environment.redact_error(e))?;
+
+            let output =
+                serde_json::from_str(&output_json_str).map_err(|e| AppError::Internal(e.to_string()))?;
+
+            Ok(Invocation {
+                version,
+                output,
+                secrets: environment.secrets,
+            })
+        }
+        .instrument(span)
+        .await
+    }
+}
+
+/// Splits `{bucket}/{key}` of a guest call
+pub fn split_target(name: &str) -> Result<(&str, &str), AppError> {
+    name.split_once('/')
+        .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+        .ok_or_else(|| AppError::BadRequest(format!("Invalid function {}, expected bucket/key", name)))
+}
@@ -0,0 +1,444 @@
+use crate::error::AppError;
+use crate::tools::invoke::Invoker;
+use crate::tools::s3::S3;
+use crate::tools::store::Store;
+use crate::tools::webhook::{Callback, DeliveryAttempt, DeliveryStatus, Outcome, Webhooks};
+use crate::tools::{env_or, unix_now};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Longest retention a caller can ask for
+const MAX_RETENTION: u64 = 30 * 24 * 60 * 60;
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+/// Error of a failed job, `status` is the HTTP status the sync call would have returned
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JobError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl From<&AppError> for JobError {
+    fn from(err: &AppError) -> JobError {
+        let (status, message) = err.status_and_message();
+        JobError {
+            status: status.as_u16(),
+            message,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Job {
+    pub id: String,
+    pub bucket: String,
+    pub key: String,
+    pub version: Option<String>,
+    pub status: JobStatus,
+    pub input: serde_json::Value,
+    pub output: Option<serde_json::Value>,
+    pub error: Option<JobError>,
+    /// Seconds the job is kept once finished
+    pub retention: u64,
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback: Option<Callback>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delivery: Option<DeliveryStatus>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deliveries: Vec<DeliveryAttempt>,
+}
+
+impl Job {
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.finished_at.is_some_and(|t| t + self.retention <= now)
+    }
+
+    /// Copy without the callback secrets, for API responses
+    pub fn redacted(mut self) -> Job {
+        self.callback = self.callback.map(|c| c.redacted());
+        self
+    }
+}
+
+/// Body of the callback of a finished job
+#[derive(Serialize)]
+struct CallbackPayload<'a> {
+    id: &'a str,
+    bucket: &'a str,
+    key: &'a str,
+    version: Option<&'a str>,
+    status: JobStatus,
+    output: Option<&'a serde_json::Value>,
+    error: Option<&'a JobError>,
+    finished_at: Option<u64>,
+}
+
+impl<'a> From<&'a Job> for CallbackPayload<'a> {
+    fn from(job: &'a Job) -> CallbackPayload<'a> {
+        CallbackPayload {
+            id: &job.id,
+            bucket: &job.bucket,
+            key: &job.key,
+            version: job.version.as_deref(),
+            status: job.status,
+            output: job.output.as_ref(),
+            error: job.error.as_ref(),
+            finished_at: job.finished_at,
+        }
+    }
+}
+
+/// Job known to this server, the lock serializes its status transitions
+#[derive(Clone)]
+struct Live {
+    token: CancellationToken,
+    lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+/// Asynchronous executions on a bounded pool of workers.
+///
+/// Jobs are persisted in the system bucket, so results survive restarts and
+/// queued jobs are picked up again on the next start.
+pub struct Jobs {
+    invoker: Arc<Invoker>,
+    webhooks: Webhooks,
+    store: Store,
+    retention: u64,
+    queue: mpsc::Sender<String>,
+    live: Mutex<HashMap<String, Live>>,
+}
+
+impl Jobs {
+    /// Reads `FAAS_SYSTEM_BUCKET`, `FAAS_JOB_WORKERS`, `FAAS_JOB_QUEUE`, `FAAS_JOB_RETENTION_SECS`
+    /// and the webhook settings
+    pub async fn start(s3: Arc<S3>, invoker: Arc<Invoker>) -> Result<Arc<Jobs>, AppError> {
+        let workers = env_or("FAAS_JOB_WORKERS", 4).max(1) as usize;
+        let queue_size = env_or("FAAS_JOB_QUEUE", 1000).max(1) as usize;
+        let retention = env_or("FAAS_JOB_RETENTION_SECS", 24 * 60 * 60).min(MAX_RETENTION);
+
+        let webhooks = Webhooks::from_env(invoker.secrets.clone())?;
+        s3.ensure_bucket(&system_bucket()).await?;
+
+        let (sender, receiver) = mpsc::channel(queue_size);
+        let jobs = Arc::new(Jobs {
+            invoker,
+            webhooks,
+            store: Store::new(s3, "jobs"),
+            retention,
+            queue: sender,
+            live: Mutex::new(HashMap::new()),
+        });
+
+        let receiver = Arc::new(tokio::sync::Mutex::new(receiver));
+        for _ in 0..workers {
+            let jobs = jobs.clone();
+            let receiver = receiver.clone();
+            tokio::spawn(async move {
+                loop {
+                    let next = receiver.lock().await.recv().await;
+                    match next {
+                        Some(id) => jobs.run(&id).await,
+                        None => break,
+                    }
+                }
+            });
+        }
+
+        jobs.store.start_sweep(Job::is_expired);
+
+        jobs.recover().await?;
+        Ok(jobs)
+    }
+
+    pub async fn submit(
+        &self,
+        bucket: &str,
+        key: &str,
+        version: Option<String>,
+        input: serde_json::Value,
+        retention: Option<u64>,
+        callback: Option<Callback>,
+    ) -> Result<Job, AppError> {
+        let retention = retention.unwrap_or(self.retention);
+        if retention > MAX_RETENTION {
+            return Err(AppError::BadRequest(format!(
+                "retention must be at most {} seconds",
+                MAX_RETENTION
+            )));
+        }
+
+        if let Some(callback) = &callback {
+            self.webhooks.check(&callback.url).await?;
+        }
+
+        let job = Job {
+            id: format!("{:032x}", rand::random::<u128>()),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            version,
+            status: JobStatus::Queued,
+            input,
+            output: None,
+            error: None,
+            retention,
+            created_at: unix_now(),
+            started_at: None,
+            finished_at: None,
+            callback,
+            delivery: None,
+            deliveries: vec![],
+        };
+        self.save(&job).await?;
+        if let Err(e) = self.enqueue(&job.id) {
+            self.live.lock().unwrap().remove(&job.id);
+            self.store.delete(&job.id).await.ok();
+            return Err(e);
+        }
+
+        Ok(job)
+    }
+
+    /// Loads a job, expired jobs are reported as missing even before the sweep removed them
+    pub async fn get(&self, id: &str) -> Result<Job, AppError> {
+        validate_id(id)?;
+        self.store
+            .get::<Job>(id)
+            .await?
+            .filter(|job| !job.is_expired(unix_now()))
+            .ok_or_else(|| AppError::NotFound(format!("Job {} not found", id)))
+    }
+
+    /// Cancels a queued or running job, a job that finished in the meantime keeps its result
+    pub async fn cancel(&self, id: &str) -> Result<Job, AppError> {
+        validate_id(id)?;
+        let live = self.live.lock().unwrap().get(id).cloned();
+        let _guard = match &live {
+            Some(live) => Some(live.lock.lock().await),
+            None => None,
+        };
+
+        let mut job = self.get(id).await?;
+        if job.status.is_finished() {
+            return Err(AppError::Conflict(format!(
+                "Job {} is already {:?}",
+                id, job.status
+            )));
+        }
+
+        if let Some(live) = &live {
+            live.token.cancel();
+            self.live.lock().unwrap().remove(id);
+        }
+
+        job.status = JobStatus::Cancelled;
+        job.finished_at = Some(unix_now());
+        self.save(&job).await?;
+        Ok(job)
+    }
+
+    fn track(&self, id: &str) {
+        let live = Live {
+            token: CancellationToken::new(),
+            lock: Arc::new(tokio::sync::Mutex::new(())),
+        };
+        self.live.lock().unwrap().insert(id.to_string(), live);
+    }
+
+    fn enqueue(&self, id: &str) -> Result<(), AppError> {
+        self.track(id);
+        self.queue.try_send(id.to_string()).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => AppError::Unavailable("Job queue is full".to_string()),
+            mpsc::error::TrySendError::Closed(_) => AppError::Internal("Job queue is closed".to_string()),
+        })
+    }
+
+    async fn run(self: &Arc<Self>, id: &str) {
+        let live = self.live.lock().unwrap().get(id).cloned();
+        let Some(live) = live.filter(|l| !l.token.is_cancelled()) else {
+            return;
+        };
+
+        let mut job = {
+            let _guard = live.lock.lock().await;
+            if live.token.is_cancelled() {
+                return;
+            }
+            let mut job = match self.get(id).await {
+                Ok(job) if job.status == JobStatus::Queued => job,
+                Ok(_) => {
+                    self.live.lock().unwrap().remove(id);
+                    return;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to load job {}: {}", id, e);
+                    self.live.lock().unwrap().remove(id);
+                    return;
+                }
+            };
+
+            job.status = JobStatus::Running;
+            job.started_at = Some(unix_now());
+            if let Err(e) = self.save(&job).await {
+                tracing::warn!("Failed to start job {}: {}", id, e);
+            }
+            job
+        };
+
+        let result = tokio::select! {
+            _ = live.token.cancelled() => {
+                // `cancel` already recorded the job
+                return;
+            }
+            result = self.invoker.invoke(&job.bucket, &job.key, job.version.clone(), &job.input) => result,
+        };
+
+        // A cancel that got the lock first wins, otherwise it sees the finished job
+        let _guard = live.lock.lock().await;
+        if live.token.is_cancelled() {
+            return;
+        }
+
+        match result {
+            Ok(invocation) => {
+                job.status = JobStatus::Succeeded;
+                job.output = Some(invocation.recorded_output());
+                job.version = invocation.version;
+            }
+            Err(e) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(JobError::from(&e));
+            }
+        }
+        job.finished_at = Some(unix_now());
+        if job.callback.is_some() {
+            job.delivery = Some(DeliveryStatus::Pending);
+        }
+
+        if let Err(e) = self.save(&job).await {
+            tracing::warn!("Failed to record result of job {}: {}", id, e);
+        }
+        // Only once the result is stored, a cancel without the lock then sees the finished job
+        self.live.lock().unwrap().remove(id);
+        self.spawn_delivery(job);
+    }
+
+    /// Delivers the callback of a finished job in the background, the worker is free again
+    fn spawn_delivery(self: &Arc<Self>, job: Job) {
+        if job.delivery != Some(DeliveryStatus::Pending) {
+            return;
+        }
+        let jobs = self.clone();
+        tokio::spawn(async move { jobs.deliver(job).await });
+    }
+
+    async fn deliver(&self, mut job: Job) {
+        let Some(callback) = job.callback.clone() else {
+            return;
+        };
+        let body = match serde_json::to_vec(&CallbackPayload::from(&job)) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!("Failed to serialize callback of job {}: {}", job.id, e);
+                return;
+            }
+        };
+
+        // Attempts recorded before a restart count towards the limit
+        let first = job.deliveries.len() as u32 + 1;
+        for attempt in first..=self.webhooks.attempts.max(first) {
+            let (record, outcome) = self.webhooks.send(&callback, &job.id, &body, attempt).await;
+            job.deliveries.push(record);
+            let status = match outcome {
+                Outcome::Delivered => DeliveryStatus::Delivered,
+                Outcome::Retry if attempt < self.webhooks.attempts => DeliveryStatus::Pending,
+                Outcome::Retry | Outcome::Failed => DeliveryStatus::Failed,
+            };
+            job.delivery = Some(status);
+
+            if let Err(e) = self.save(&job).await {
+                tracing::warn!("Failed to record delivery of job {}: {}", job.id, e);
+            }
+            if status != DeliveryStatus::Pending {
+                return;
+            }
+            tokio::time::sleep(self.webhooks.backoff(attempt)).await;
+        }
+    }
+
+    /// Queues the jobs that were waiting before a restart, running ones are failed
+    /// and pending callbacks are delivered again
+    async fn recover(self: &Arc<Self>) -> Result<(), AppError> {
+        let mut queued = Vec::new();
+        for mut job in self.store.list::<Job>().await? {
+            match job.status {
+                JobStatus::Queued => {
+                    self.track(&job.id);
+                    queued.push(job.id);
+                }
+                JobStatus::Running => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(JobError::from(&AppError::Unavailable(
+                        "Server restarted during the execution".to_string(),
+                    )));
+                    job.finished_at = Some(unix_now());
+                    if job.callback.is_some() {
+                        job.delivery = Some(DeliveryStatus::Pending);
+                    }
+                    self.save(&job).await?;
+                    self.spawn_delivery(job);
+                }
+                _ => self.spawn_delivery(job),
+            }
+        }
+
+        // More jobs may have been waiting than the queue holds, they are fed as workers free up
+        let queue = self.queue.clone();
+        tokio::spawn(async move {
+            for id in queued {
+                if queue.send(id).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn save(&self, job: &Job) -> Result<(), AppError> {
+        self.store.put(&job.id, job).await
+    }
+}
+
+/// Bucket for the server's own state (jobs, ...), `FAAS_SYSTEM_BUCKET`
+pub fn system_bucket() -> String {
+    std::env::var("FAAS_SYSTEM_BUCKET").unwrap_or_else(|_| "faas-system".to_string())
+}
+
+/// Ids of jobs and workflow runs are 32 hex digits
+pub fn validate_id(id: &str) -> Result<(), AppError> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::BadRequest(format!("Invalid id {}", id)));
+    }
+    Ok(())
+}
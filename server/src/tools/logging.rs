@@ -0,0 +1,198 @@
+use crate::tools::config::redact_text;
+use crate::tools::env_or;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Level of a guest log record, also the per-function threshold
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// Reads `FAAS_GUEST_LOG_LEVEL`, `info` when unset or invalid
+    pub fn from_env() -> LogLevel {
+        std::env::var("FAAS_GUEST_LOG_LEVEL")
+            .ok()
+            .and_then(|v| serde_json::from_value(serde_json::Value::String(v.to_lowercase())).ok())
+            .unwrap_or(LogLevel::Info)
+    }
+}
+
+/// Maximum length of a message or field value, longer ones are truncated
+const MAX_TEXT: usize = 4096;
+
+const MAX_FIELDS: usize = 32;
+
+/// Token buckets of the functions, shared by all their executions
+#[derive(Default)]
+pub struct LogRates {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl LogRates {
+    /// Takes a token of `function`, refilled with `rate` records per second
+    fn take(&self, function: &str, rate: f64) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if !buckets.contains_key(function) && buckets.len() >= 1024 {
+            // Buckets idle for a second are full again, they are created anew when needed
+            buckets.retain(|_, bucket| now.duration_since(bucket.refilled) < Duration::from_secs(1));
+        }
+        let bucket = buckets.entry(function.to_string()).or_insert(TokenBucket {
+            // A burst of one second is allowed at the start
+            tokens: rate,
+            refilled: now,
+        });
+
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(rate);
+        bucket.refilled = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Turns the `local:faas/logging` records of one execution into `tracing` events
+pub struct GuestLogger {
+    function: String,
+    version: Option<String>,
+    invocation: String,
+    level: LogLevel,
+    /// Secret values of the function, replaced in messages and fields
+    secrets: Vec<String>,
+    /// Records per second of the function, over all its executions
+    rate: f64,
+    rates: Arc<LogRates>,
+    /// Records of this execution dropped by the rate limit
+    dropped: u64,
+}
+
+impl GuestLogger {
+    /// `level` and `rate` fall back to `FAAS_GUEST_LOG_LEVEL` and `FAAS_GUEST_LOG_RATE` (default 100)
+    pub fn new(
+        function: String,
+        version: Option<String>,
+        invocation: String,
+        level: Option<LogLevel>,
+        rate: Option<u32>,
+        secrets: Vec<String>,
+        rates: Arc<LogRates>,
+    ) -> GuestLogger {
+        let rate = rate.map_or_else(|| env_or("FAAS_GUEST_LOG_RATE", 100), u64::from) as f64;
+        GuestLogger {
+            function,
+            version,
+            invocation,
+            level: level.unwrap_or_else(LogLevel::from_env),
+            secrets,
+            rate,
+            rates,
+            dropped: 0,
+        }
+    }
+
+    /// Records below the threshold are ignored, records over the rate are counted and dropped
+    pub fn log(&mut self, level: LogLevel, message: &str, fields: &[(String, String)]) {
+        if level < self.level || !self.take() {
+            return;
+        }
+
+        let message = self.clean(message);
+        let fields = fields
+            .iter()
+            .take(MAX_FIELDS)
+            .map(|(key, value)| (self.clean(key), serde_json::Value::String(self.clean(value))))
+            .collect::<serde_json::Map<_, _>>();
+        // One JSON string, field names of `tracing` events are fixed at compile time
+        let fields = serde_json::Value::Object(fields).to_string();
+        let version = self.version.as_deref().unwrap_or_default();
+
+        macro_rules! emit {
+            ($level:expr) => {
+                tracing::event!(
+                    target: "faas::guest",
+                    $level,
+                    function = %self.function,
+                    version,
+                    invocation = %self.invocation,
+                    fields = %fields,
+                    "{}",
+                    message
+                )
+            };
+        }
+        match level {
+            LogLevel::Trace => emit!(tracing::Level::TRACE),
+            LogLevel::Debug => emit!(tracing::Level::DEBUG),
+            LogLevel::Info => emit!(tracing::Level::INFO),
+            LogLevel::Warn => emit!(tracing::Level::WARN),
+            LogLevel::Error => emit!(tracing::Level::ERROR),
+        }
+    }
+
+    /// Captured stdout of the guest, every line becomes an `info` record with `stream = "stdout"`
+    pub fn stdout(&mut self, output: &[u8]) {
+        let mut output = String::from_utf8_lossy(output).into_owned();
+        // Before splitting, a secret could span lines
+        redact_text(&self.secrets, &mut output);
+        let fields = [("stream".to_string(), "stdout".to_string())];
+        for line in output.lines().filter(|line| !line.is_empty()) {
+            self.log(LogLevel::Info, line, &fields);
+        }
+    }
+
+    fn take(&mut self) -> bool {
+        let taken = self.rates.take(&self.function, self.rate);
+        if !taken {
+            self.dropped += 1;
+        }
+        taken
+    }
+
+    /// Redacts before truncating, a cut could leave part of a secret
+    fn clean(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        redact_text(&self.secrets, &mut text);
+        if text.len() > MAX_TEXT {
+            let mut end = MAX_TEXT;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            text.truncate(end);
+            text.push_str("...");
+        }
+        text
+    }
+}
+
+impl Drop for GuestLogger {
+    fn drop(&mut self) {
+        if self.dropped > 0 {
+            tracing::warn!(
+                target: "faas::guest",
+                function = %self.function,
+                invocation = %self.invocation,
+                dropped = self.dropped,
+                "Rate limit of {} records per second exceeded",
+                self.rate
+            );
+        }
+    }
+}
@@ -1 +1,50 @@
-pub mod s3;
\ No newline at end of file
+pub mod s3;
+pub mod alias;
+pub mod runtime;
+pub mod inspect;
+pub mod registry;
+pub mod validate;
+pub mod adapt;
+pub mod upload;
+pub mod cache;
+pub mod signing;
+pub mod names;
+pub mod invoke;
+pub mod jobs;
+pub mod webhook;
+pub mod scheduler;
+pub mod triggers;
+pub mod pipeline;
+pub mod workflow;
+pub mod compose;
+pub mod secrets;
+pub mod config;
+pub mod logging;
+pub mod store;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Retry delays never grow past 5 minutes
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// Seconds since the unix epoch, used for all persisted timestamps
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Numeric setting from the environment, `default` when unset or invalid
+pub fn env_or(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Delay before the attempt following `attempt`, `base` doubled for every attempt
+pub fn backoff(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_BACKOFF)
+}
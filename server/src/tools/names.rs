@@ -0,0 +1,158 @@
+use crate::error::AppError;
+use crate::tools::jobs::system_bucket;
+use crate::tools::s3::INTERNAL_PREFIX;
+
+/// S3 limits keys to 1024 bytes of UTF-8
+const MAX_KEY_LENGTH: usize = 1024;
+
+/// Segments addressing the sub-resources of a file, they can't be part of a key
+const RESERVED_SEGMENTS: [&str; 5] = ["describe", "versions", "presign", "finalize", "compose"];
+
+/// Target of a `/file/{bucket}/{*path}` request, the key followed by an optional sub-resource
+#[derive(Debug, PartialEq)]
+pub enum FileRoute {
+    Object(String),
+    Describe(String),
+    Versions(String),
+    Version(String, String),
+    Restore(String, String),
+    Presign(String),
+    Finalize(String),
+    Compose(String),
+}
+
+impl FileRoute {
+    /// Splits the reserved trailing segments off the path and validates the key
+    pub fn parse(path: &str) -> Result<FileRoute, AppError> {
+        let segments: Vec<&str> = path.split('/').collect();
+        let key = |n: usize| segments[..segments.len() - n].join("/");
+
+        let route = match segments.as_slice() {
+            [_, .., "versions", version, "restore"] => FileRoute::Restore(key(3), version.to_string()),
+            [_, .., "versions", version] => FileRoute::Version(key(2), version.to_string()),
+            [_, .., "versions"] => FileRoute::Versions(key(1)),
+            [_, .., "describe"] => FileRoute::Describe(key(1)),
+            [_, .., "presign"] => FileRoute::Presign(key(1)),
+            [_, .., "finalize"] => FileRoute::Finalize(key(1)),
+            [_, .., "compose"] => FileRoute::Compose(key(1)),
+            _ => FileRoute::Object(path.to_string()),
+        };
+
+        validate_key(route.key())?;
+        Ok(route)
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            FileRoute::Object(key)
+            | FileRoute::Describe(key)
+            | FileRoute::Versions(key)
+            | FileRoute::Version(key, _)
+            | FileRoute::Restore(key, _)
+            | FileRoute::Presign(key)
+            | FileRoute::Finalize(key)
+            | FileRoute::Compose(key) => key,
+        }
+    }
+}
+
+/// Checks a bucket name against the S3 naming rules
+pub fn validate_bucket(bucket: &str) -> Result<(), AppError> {
+    let invalid = |reason: &str| {
+        Err(AppError::BadRequest(format!(
+            "Invalid bucket name {}: {}",
+            bucket, reason
+        )))
+    };
+
+    if bucket.len() < 3 || bucket.len() > 63 {
+        return invalid("must be between 3 and 63 characters long");
+    }
+    if !bucket
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'.' || b == b'-')
+    {
+        return invalid("only lowercase letters, digits, dots and hyphens are allowed");
+    }
+    let first_last = [bucket.as_bytes()[0], bucket.as_bytes()[bucket.len() - 1]];
+    if !first_last.iter().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit()) {
+        return invalid("must begin and end with a letter or digit");
+    }
+    if bucket.contains("..") || bucket.contains(".-") || bucket.contains("-.") {
+        return invalid("dots can't be adjacent to dots or hyphens");
+    }
+    if bucket.parse::<std::net::Ipv4Addr>().is_ok() {
+        return invalid("must not be formatted as an IP address");
+    }
+    if bucket.starts_with("xn--") || bucket.starts_with("sthree-") {
+        return invalid("reserved prefix");
+    }
+    if bucket.ends_with("-s3alias") || bucket.ends_with("--ol-s3") {
+        return invalid("reserved suffix");
+    }
+    // Jobs, schedules, triggers, workflows and sealed secrets, only the server touches it
+    if bucket == system_bucket() {
+        return Err(AppError::Forbidden(format!(
+            "Bucket {} holds the server's state",
+            bucket
+        )));
+    }
+
+    Ok(())
+}
+
+/// Checks an object key, segments are separated by `/` and must not be empty
+pub fn validate_key(key: &str) -> Result<(), AppError> {
+    let invalid = |reason: &str| Err(AppError::BadRequest(format!("Invalid key {}: {}", key, reason)));
+
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return invalid("must be between 1 and 1024 bytes long");
+    }
+    if key.chars().any(|c| c.is_control()) {
+        return invalid("control characters are not allowed");
+    }
+    if key.contains('@') {
+        return invalid("@ separates the alias of a function");
+    }
+    if key.starts_with(INTERNAL_PREFIX) {
+        return invalid("reserved for the server");
+    }
+    for segment in key.split('/') {
+        if segment.is_empty() || segment == "." || segment == ".." {
+            return invalid("empty, . and .. segments are not allowed");
+        }
+        if RESERVED_SEGMENTS.contains(&segment) {
+            return invalid("describe, versions, presign, finalize and compose are reserved segments");
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_object(bucket: &str, key: &str) -> Result<(), AppError> {
+    validate_bucket(bucket)?;
+    validate_key(key)
+}
+
+/// Names of schedules and triggers, they are used in URLs and object keys
+pub fn validate_name(kind: &str, name: &str) -> Result<(), AppError> {
+    if name.is_empty()
+        || name.len() > 64
+        || !name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+    {
+        return Err(AppError::BadRequest(format!(
+            "Invalid {} name {}, use up to 64 lowercase letters, digits, - and _",
+            kind, name
+        )));
+    }
+    Ok(())
+}
+
+/// Splits `team/fn.wasm/prod` into the key and its last segment
+pub fn split_last(path: &str) -> Result<(&str, &str), AppError> {
+    path.rsplit_once('/')
+        .filter(|(key, last)| !key.is_empty() && !last.is_empty())
+        .ok_or_else(|| AppError::NotFound(format!("No route for {}", path)))
+}
@@ -0,0 +1,229 @@
+use crate::error::AppError;
+use crate::tools::invoke::Invoker;
+use crate::tools::jobs::JobError;
+use crate::tools::s3::S3;
+use crate::tools::store::Store;
+use crate::tools::{alias, names};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Upper bound of steps, a pipeline runs within a single request
+const MAX_STEPS: usize = 32;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OnError {
+    /// The pipeline ends with the failed step
+    #[default]
+    Stop,
+    /// The step is passed over, the next step gets the same input
+    Continue,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Step {
+    /// Label in the results, defaults to the key
+    #[serde(default)]
+    pub name: Option<String>,
+    pub bucket: String,
+    /// Function key or `key@alias`
+    pub key: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub on_error: OnError,
+}
+
+impl Step {
+    fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.key.clone())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pipeline {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub steps: Vec<Step>,
+    #[serde(default)]
+    pub created_at: u64,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Succeeded,
+    Failed,
+    /// Not executed because an earlier step stopped the pipeline
+    Skipped,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StepResult {
+    pub name: String,
+    pub bucket: String,
+    pub key: String,
+    pub version: Option<String>,
+    pub status: StepStatus,
+    pub duration_ms: u64,
+    pub output: Option<serde_json::Value>,
+    pub error: Option<JobError>,
+}
+
+/// Result of a pipeline execution, with the partial results of every step
+#[derive(Serialize, Debug, Clone)]
+pub struct PipelineRun {
+    pub pipeline: String,
+    pub status: StepStatus,
+    /// Output of the last executed step
+    pub output: Option<serde_json::Value>,
+    /// Error of the step that stopped the pipeline
+    pub error: Option<JobError>,
+    pub duration_ms: u64,
+    pub steps: Vec<StepResult>,
+}
+
+/// Pipeline definitions in the system bucket, executed step by step through the `Invoker`
+pub struct Pipelines {
+    invoker: Arc<Invoker>,
+    store: Store,
+}
+
+impl Pipelines {
+    pub fn new(s3: Arc<S3>, invoker: Arc<Invoker>) -> Pipelines {
+        Pipelines {
+            invoker,
+            store: Store::new(s3, "pipelines"),
+        }
+    }
+
+    pub async fn list(&self) -> Result<Vec<Pipeline>, AppError> {
+        let mut pipelines: Vec<Pipeline> = self.store.list().await?;
+        pipelines.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(pipelines)
+    }
+
+    pub async fn get(&self, name: &str) -> Result<Pipeline, AppError> {
+        names::validate_name("pipeline", name)?;
+        self.store
+            .get(name)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Pipeline {} not found", name)))
+    }
+
+    pub async fn put(&self, mut pipeline: Pipeline) -> Result<Pipeline, AppError> {
+        names::validate_name("pipeline", &pipeline.name)?;
+        if pipeline.steps.is_empty() || pipeline.steps.len() > MAX_STEPS {
+            return Err(AppError::BadRequest(format!(
+                "A pipeline needs 1 to {} steps",
+                MAX_STEPS
+            )));
+        }
+        for step in &pipeline.steps {
+            let (key, _) = alias::split_alias(&step.key);
+            names::validate_object(&step.bucket, key)?;
+        }
+
+        if let Ok(existing) = self.get(&pipeline.name).await {
+            pipeline.created_at = existing.created_at;
+        }
+        self.store.put(&pipeline.name, &pipeline).await?;
+        Ok(pipeline)
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<(), AppError> {
+        self.get(name).await?;
+        self.store.delete(name).await
+    }
+
+    /// Feeds `input` to the first step and every output to the next step
+    pub async fn run(&self, name: &str, input: serde_json::Value) -> Result<PipelineRun, AppError> {
+        let pipeline = self.get(name).await?;
+        let start = Instant::now();
+
+        let mut run = PipelineRun::new(&pipeline);
+        for step in &pipeline.steps {
+            if run.status == StepStatus::Failed {
+                run.skip(step);
+                continue;
+            }
+
+            // A step that failed and continued leaves the input of the next step unchanged
+            let current = run.output.as_ref().unwrap_or(&input);
+            let step_start = Instant::now();
+            let invocation = self
+                .invoker
+                .invoke(&step.bucket, &step.key, step.version.clone(), current)
+                .await
+                .map(|invocation| (invocation.version, invocation.output));
+            run.record(step, invocation, step_start.elapsed().as_millis() as u64);
+        }
+
+        run.duration_ms = start.elapsed().as_millis() as u64;
+        Ok(run)
+    }
+}
+
+impl PipelineRun {
+    pub fn new(pipeline: &Pipeline) -> PipelineRun {
+        PipelineRun {
+            pipeline: pipeline.name.clone(),
+            status: StepStatus::Succeeded,
+            output: None,
+            error: None,
+            duration_ms: 0,
+            steps: Vec::with_capacity(pipeline.steps.len()),
+        }
+    }
+
+    fn result(step: &Step, status: StepStatus) -> StepResult {
+        StepResult {
+            name: step.label(),
+            bucket: step.bucket.clone(),
+            key: step.key.clone(),
+            version: step.version.clone(),
+            status,
+            duration_ms: 0,
+            output: None,
+            error: None,
+        }
+    }
+
+    /// Step not executed because an earlier one stopped the pipeline
+    pub fn skip(&mut self, step: &Step) {
+        self.steps.push(PipelineRun::result(step, StepStatus::Skipped));
+    }
+
+    /// Adds the outcome of a step, the version and output of a success or the error
+    pub fn record(
+        &mut self,
+        step: &Step,
+        outcome: Result<(Option<String>, serde_json::Value), AppError>,
+        duration_ms: u64,
+    ) {
+        let mut result = PipelineRun::result(step, StepStatus::Succeeded);
+        result.duration_ms = duration_ms;
+
+        match outcome {
+            Ok((version, output)) => {
+                result.version = version;
+                result.output = Some(output.clone());
+                self.output = Some(output);
+            }
+            Err(e) => {
+                let error = JobError::from(&e);
+                result.status = StepStatus::Failed;
+                result.error = Some(error.clone());
+                if step.on_error == OnError::Stop {
+                    self.status = StepStatus::Failed;
+                    self.error = Some(error);
+                }
+            }
+        }
+        self.steps.push(result);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::error::AppError;
+use crate::tools::inspect::ComponentInfo;
+use crate::tools::logging::LogLevel;
+use crate::tools::s3::{S3, INTERNAL_PREFIX};
+use crate::tools::unix_now;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Serializes the read-modify-write updates of registry entries within this server,
+/// concurrent uploads would otherwise drop each other's `versions` entries
+static UPDATES: Mutex<()> = Mutex::const_new(());
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Limits {
+    pub timeout_ms: Option<u64>,
+    pub memory_mb: Option<u64>,
+    /// Threshold of `local:faas/logging` records, `FAAS_GUEST_LOG_LEVEL` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<LogLevel>,
+    /// Log records per second, `FAAS_GUEST_LOG_RATE` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logs_per_second: Option<u32>,
+    /// Buckets besides its own whose functions it may call through `local:faas/invoke`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub call_buckets: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FunctionVersion {
+    pub version: Option<String>,
+    pub size: u64,
+    pub sha256: String,
+    /// Detached ed25519 signature of the sha256 digest, hex encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FunctionMetadata {
+    pub namespace: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub owner: Option<String>,
+    pub world: Option<String>,
+    pub exports: Vec<String>,
+    pub imports: Vec<String>,
+    pub size: u64,
+    pub sha256: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub versions: Vec<FunctionVersion>,
+    #[serde(default)]
+    pub limits: Limits,
+}
+
+impl FunctionMetadata {
+    /// Version recorded on upload, `None` selects the current object
+    pub fn version_for(&self, version: Option<&str>) -> Option<&FunctionVersion> {
+        match version {
+            None => self.versions.last(),
+            Some(version) => self
+                .versions
+                .iter()
+                .find(|v| v.version.as_deref() == Some(version)),
+        }
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn meta_prefix() -> String {
+    format!("{}meta/", INTERNAL_PREFIX)
+}
+
+fn meta_key(name: &str) -> String {
+    format!("{}{}.json", meta_prefix(), name)
+}
+
+pub async fn load(
+    s3: &S3,
+    namespace: &str,
+    name: &str,
+) -> Result<Option<FunctionMetadata>, AppError> {
+    s3.get_json(namespace, &meta_key(name)).await
+}
+
+/// Held from loading an entry until the updated entry is saved
+pub async fn lock_updates() -> MutexGuard<'static, ()> {
+    UPDATES.lock().await
+}
+
+pub async fn save(s3: &S3, meta: &FunctionMetadata) -> Result<(), AppError> {
+    s3.put_json(&meta.namespace, &meta_key(&meta.name), meta).await
+}
+
+pub async fn remove(s3: &S3, namespace: &str, name: &str) -> Result<(), AppError> {
+    s3.delete_file(namespace, &meta_key(name)).await
+}
+
+pub async fn list(s3: &S3, namespace: &str) -> Result<Vec<FunctionMetadata>, AppError> {
+    let mut functions = Vec::new();
+    for key in s3.list_keys(namespace, &meta_prefix()).await? {
+        if let Some(meta) = s3.get_json(namespace, &key).await? {
+            functions.push(meta);
+        }
+    }
+    Ok(functions)
+}
+
+/// Updates the metadata derived from an uploaded component, keeping the user defined fields
+pub async fn record_upload(
+    s3: &S3,
+    namespace: &str,
+    name: &str,
+    version: FunctionVersion,
+    info: ComponentInfo,
+) -> Result<FunctionMetadata, AppError> {
+    let now = unix_now();
+
+    let _updates = lock_updates().await;
+    let mut meta = load(s3, namespace, name).await?.unwrap_or(FunctionMetadata {
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        description: None,
+        owner: None,
+        world: None,
+        exports: vec![],
+        imports: vec![],
+        size: 0,
+        sha256: String::new(),
+        created_at: now,
+        updated_at: now,
+        versions: vec![],
+        limits: Limits::default(),
+    });
+
+    meta.world = info.world;
+    meta.imports = info.imports;
+    meta.exports = info.exports;
+    meta.size = version.size;
+    meta.sha256 = version.sha256.clone();
+    meta.updated_at = now;
+    meta.versions.push(version);
+
+    save(s3, &meta).await?;
+    Ok(meta)
+}
+
+/// Records a restored version as new current version, with the digest of the original
+/// or, for versions uploaded before they were recorded, of the restored object
+pub async fn record_restore(
+    s3: &S3,
+    namespace: &str,
+    name: &str,
+    from_version: &str,
+    new_version: Option<String>,
+) -> Result<Option<FunctionMetadata>, AppError> {
+    let _updates = lock_updates().await;
+    let Some(mut meta) = load(s3, namespace, name).await? else {
+        return Ok(None);
+    };
+
+    let now = unix_now();
+    let restored = match meta.version_for(Some(from_version)).cloned() {
+        Some(original) => FunctionVersion {
+            version: new_version,
+            created_at: now,
+            ..original
+        },
+        None => {
+            let (size, sha256) = digest_object(s3, namespace, name, new_version.as_deref()).await?;
+            FunctionVersion {
+                version: new_version,
+                size,
+                sha256,
+                signature: None,
+                created_at: now,
+            }
+        }
+    };
+
+    meta.size = restored.size;
+    meta.sha256 = restored.sha256.clone();
+    meta.updated_at = now;
+    meta.versions.push(restored);
+
+    save(s3, &meta).await?;
+    Ok(Some(meta))
+}
+
+/// Size and sha256 of a stored object, hashed while streaming
+async fn digest_object(
+    s3: &S3,
+    namespace: &str,
+    name: &str,
+    version: Option<&str>,
+) -> Result<(u64, String), AppError> {
+    let mut body = s3.download_file_version(namespace, name, version).await?;
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    while let Some(chunk) = body
+        .try_next()
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+    {
+        hasher.update(&chunk);
+        size += chunk.len() as u64;
+    }
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+pub async fn remove_version(
+    s3: &S3,
+    namespace: &str,
+    name: &str,
+    version: &str,
+) -> Result<(), AppError> {
+    let _updates = lock_updates().await;
+    let Some(mut meta) = load(s3, namespace, name).await? else {
+        return Ok(());
+    };
+
+    meta.versions.retain(|v| v.version.as_deref() != Some(version));
+    if let Some(current) = meta.versions.last() {
+        meta.size = current.size;
+        meta.sha256 = current.sha256.clone();
+    }
+    meta.updated_at = unix_now();
+
+    save(s3, &meta).await
+}
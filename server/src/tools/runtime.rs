@@ -0,0 +1,223 @@
+use crate::error::AppError;
+use crate::tools::invoke::CallContext;
+use crate::tools::logging::{GuestLogger, LogLevel};
+use crate::tools::env_or;
+use crate::tools::registry::Limits;
+use std::time::Duration;
+use wasmtime::component::{Component, HasSelf, Linker, ResourceTable};
+use wasmtime::{Config, Engine, OptLevel, Store, StoreLimits, StoreLimitsBuilder};
+use wasmtime_wasi::p2::pipe::MemoryOutputPipe;
+use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiCtxView, WasiView};
+use wasmtime_wasi_config::{WasiConfig, WasiConfigVariables};
+use wasmtime_wasi_http::{WasiHttpCtx, WasiHttpView};
+
+/// Interval of the epoch ticker, the granularity of execution timeouts
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+pub struct ServerState {
+    ctx: WasiCtx,
+    table: ResourceTable,
+    http: WasiHttpCtx,
+    limits: StoreLimits,
+    /// Config and secrets of the function, for `wasi:config/store`
+    config: WasiConfigVariables,
+    /// Caller of the running function, for `local:faas/invoke`
+    invoke: CallContext,
+    logger: GuestLogger,
+    /// Message of `local:faas/outcome/fail`, turns the execution into an error
+    failure: Option<String>,
+}
+
+impl WasiView for ServerState {
+    fn ctx(&mut self) -> WasiCtxView<'_> {
+        WasiCtxView {
+            ctx: &mut self.ctx,
+            table: &mut self.table,
+        }
+    }
+}
+
+impl WasiHttpView for ServerState {
+    fn ctx(&mut self) -> &mut WasiHttpCtx {
+        &mut self.http
+    }
+
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+wasmtime::component::bindgen!({
+    world: "faas-exec",
+    path: "../wit",
+    imports: {
+        "local:faas/invoke.call": async
+    },
+    exports: {
+        "exec": async
+    }
+});
+
+impl local::faas::invoke::Host for ServerState {
+    async fn call(&mut self, name: String, input: String) -> Result<String, local::faas::invoke::Error> {
+        self.invoke.call(&name, &input).await.map_err(|e| {
+            tracing::debug!("Call of {} failed: {}", name, e);
+            let (status, message) = e.status_and_message();
+            local::faas::invoke::Error {
+                status: status.as_u16(),
+                message,
+            }
+        })
+    }
+}
+
+impl local::faas::outcome::Host for ServerState {
+    fn fail(&mut self, message: String) {
+        self.failure = Some(message);
+    }
+}
+
+impl local::faas::logging::Host for ServerState {
+    fn log(&mut self, level: local::faas::logging::Level, message: String, fields: Vec<(String, String)>) {
+        use local::faas::logging::Level;
+
+        let level = match level {
+            Level::Trace => LogLevel::Trace,
+            Level::Debug => LogLevel::Debug,
+            Level::Info => LogLevel::Info,
+            Level::Warn => LogLevel::Warn,
+            Level::Error => LogLevel::Error,
+        };
+        self.logger.log(level, &message, &fields);
+    }
+}
+
+/// Shared wasmtime engine and linker used by all executions
+pub struct Runtime {
+    pub engine: Engine,
+    linker: Linker<ServerState>,
+    /// Linker with a placeholder `next`, only used to type-check stages
+    stage_linker: Linker<ServerState>,
+}
+
+impl Runtime {
+    pub fn new() -> Result<Self, AppError> {
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        config.async_support(true);
+        config.debug_info(true);
+        config.cranelift_opt_level(OptLevel::None);
+        config.epoch_interruption(true);
+
+        let engine = Engine::new(&config)?;
+
+        let linker = wasi_linker(&engine)?;
+        let mut stage_linker = wasi_linker(&engine)?;
+        stage_linker
+            .root()
+            .func_wrap_async("next", |_, (_input,): (String,)| {
+                Box::new(async { Err::<(String,), _>(wasmtime::Error::msg("Stages only run once composed")) })
+            })?;
+
+        let ticker = engine.weak();
+        std::thread::spawn(move || {
+            while let Some(engine) = ticker.upgrade() {
+                engine.increment_epoch();
+                drop(engine);
+                std::thread::sleep(EPOCH_TICK);
+            }
+        });
+
+        Ok(Runtime {
+            engine,
+            linker,
+            stage_linker,
+        })
+    }
+
+    pub fn compile(&self, wasm_bytes: &[u8]) -> Result<Component, AppError> {
+        Ok(Component::from_binary(&self.engine, wasm_bytes)?)
+    }
+
+    /// Type-checks the component against the `faas-exec` world
+    pub fn prepare(&self, component: &Component) -> Result<FaasExecPre<ServerState>, AppError> {
+        let pre = self.linker.instantiate_pre(component)?;
+        Ok(FaasExecPre::new(pre)?)
+    }
+
+    /// Type-checks the component against the `faas-stage` world
+    pub fn prepare_stage(&self, component: &Component) -> Result<(), AppError> {
+        let pre = self.stage_linker.instantiate_pre(component)?;
+        FaasExecPre::new(pre)?;
+        Ok(())
+    }
+
+    pub async fn call(
+        &self,
+        component: &Component,
+        input: &str,
+        limits: &Limits,
+        env: &[(String, String)],
+        invoke: CallContext,
+        logger: GuestLogger,
+    ) -> Result<String, AppError> {
+        let pre = self.prepare(component)?;
+
+        let mut store_limits = StoreLimitsBuilder::new();
+        if let Some(memory_mb) = limits.memory_mb {
+            store_limits = store_limits.memory_size(memory_mb as usize * 1024 * 1024);
+        }
+
+        // Captured instead of inherited, printed secrets must not reach the server log
+        let stdout = MemoryOutputPipe::new(env_or("FAAS_GUEST_STDOUT_MAX", 1024 * 1024) as usize);
+        let mut store = Store::new(
+            &self.engine,
+            ServerState {
+                ctx: WasiCtxBuilder::new().stdout(stdout.clone()).envs(env).build(),
+                table: ResourceTable::new(),
+                http: WasiHttpCtx::new(),
+                limits: store_limits.build(),
+                config: WasiConfigVariables::from_iter(env.iter().cloned()),
+                invoke,
+                logger,
+                failure: None,
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        // Yield back to tokio on every tick so the timeout below can cancel busy guests
+        store.epoch_deadline_async_yield_and_update(1);
+
+        let call = async {
+            let bindings = pre.instantiate_async(&mut store).await?;
+            Ok::<_, AppError>(bindings.call_exec(&mut store, input).await?)
+        };
+
+        let result = match limits.timeout_ms {
+            Some(timeout_ms) => tokio::time::timeout(Duration::from_millis(timeout_ms), call)
+                .await
+                .map_err(|_| AppError::Timeout(format!("Execution timed out after {}ms", timeout_ms)))
+                .and_then(|result| result),
+            None => call.await,
+        };
+        let state = store.data_mut();
+        state.logger.stdout(&stdout.contents());
+        match state.failure.take() {
+            // A failure reported by the function is its answer, retrying won't change it
+            Some(message) if result.is_ok() => Err(AppError::Validation(message)),
+            _ => result,
+        }
+    }
+}
+
+fn wasi_linker(engine: &Engine) -> Result<Linker<ServerState>, AppError> {
+    let mut linker = Linker::new(engine);
+    wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
+    wasmtime_wasi_http::add_only_http_to_linker_async(&mut linker)?;
+    wasmtime_wasi_config::add_to_linker(&mut linker, |state: &mut ServerState| {
+        WasiConfig::from(&state.config)
+    })?;
+    local::faas::invoke::add_to_linker::<_, HasSelf<ServerState>>(&mut linker, |state| state)?;
+    local::faas::logging::add_to_linker::<_, HasSelf<ServerState>>(&mut linker, |state| state)?;
+    local::faas::outcome::add_to_linker::<_, HasSelf<ServerState>>(&mut linker, |state| state)?;
+    Ok(linker)
+}
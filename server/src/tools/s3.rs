@@ -1,8 +1,22 @@
 use crate::error::AppError;
 use aws_config::{BehaviorVersion, Region};
+use aws_sdk_s3::config::http::HttpResponse;
 use aws_sdk_s3::config::Credentials;
-use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::primitives::{ByteStream, DateTime, DateTimeFormat};
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::presigning::{PresignedRequest as PresignedRequestSdk, PresigningConfig};
+use aws_sdk_s3::types::{
+    BucketVersioningStatus, CompletedMultipartUpload, CompletedPart, Delete, ObjectIdentifier, Tag,
+    Tagging, VersioningConfiguration,
+};
 use aws_sdk_s3::Client;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Prefix for objects the server keeps next to the functions (aliases, metadata, ...)
+pub const INTERNAL_PREFIX: &str = ".faas/";
 
 pub struct Config {
     pub region: String,
@@ -23,6 +37,114 @@ impl Config {
     }
 }
 
+/// Identity of an object after it has been written
+#[derive(Debug, Default, Clone)]
+pub struct PutResult {
+    pub version: Option<String>,
+    pub etag: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ObjectEntry {
+    pub key: String,
+    pub size: i64,
+    pub last_modified: Option<i64>,
+    pub etag: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VersionEntry {
+    pub version: String,
+    pub is_latest: bool,
+    pub size: i64,
+    pub last_modified: Option<i64>,
+    pub etag: Option<String>,
+}
+
+/// A single object version or delete marker, `version` is `None` in unversioned buckets
+#[derive(Serialize, Debug, Clone)]
+pub struct ObjectRef {
+    pub key: String,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ListPage {
+    pub objects: Vec<ObjectEntry>,
+    pub prefixes: Vec<String>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BucketEntry {
+    pub name: String,
+    pub created: Option<i64>,
+}
+
+/// S3 metadata entry of the original filename, percent-encoded as metadata is ASCII only
+const FILENAME_META: &str = "filename";
+
+/// Content type, original filename and user defined metadata stored with an object
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ObjectMeta {
+    pub content_type: Option<String>,
+    pub filename: Option<String>,
+    pub user: BTreeMap<String, String>,
+}
+
+impl ObjectMeta {
+    fn to_s3(&self) -> HashMap<String, String> {
+        let mut metadata: HashMap<String, String> = self.user.clone().into_iter().collect();
+        if let Some(filename) = &self.filename {
+            metadata.insert(FILENAME_META.to_string(), encode_key(filename));
+        }
+        metadata
+    }
+
+    fn from_s3(content_type: Option<&str>, metadata: Option<&HashMap<String, String>>) -> ObjectMeta {
+        let mut user: BTreeMap<String, String> = metadata
+            .map(|m| m.clone().into_iter().collect())
+            .unwrap_or_default();
+        let filename = user.remove(FILENAME_META).map(|f| decode_key(&f));
+
+        ObjectMeta {
+            content_type: content_type.map(|c| c.to_string()),
+            filename,
+            user,
+        }
+    }
+}
+
+/// Response headers of a stored object
+#[derive(Debug, Clone, Default)]
+pub struct ObjectInfo {
+    pub size: i64,
+    pub etag: Option<String>,
+    /// HTTP date of the last modification
+    pub last_modified: Option<String>,
+    pub version: Option<String>,
+    /// Set when only a range of the object was returned
+    pub content_range: Option<String>,
+    pub meta: ObjectMeta,
+}
+
+#[derive(Debug, Default)]
+pub struct GetOptions<'a> {
+    pub version: Option<&'a str>,
+    /// Raw `Range` header, e.g. `bytes=0-1023`
+    pub range: Option<&'a str>,
+    pub if_none_match: Option<&'a str>,
+}
+
+/// Presigned request a client sends to S3 directly
+#[derive(Serialize, Debug, Clone)]
+pub struct PresignedRequest {
+    pub method: String,
+    pub url: String,
+    /// Headers that are part of the signature and have to be sent as is
+    pub headers: Vec<(String, String)>,
+}
+
 pub struct S3 {
     pub client: aws_sdk_s3::Client,
 }
@@ -71,6 +193,17 @@ impl S3 {
         Ok(())
     }
 
+    /// Creates the bucket unless it already exists
+    pub async fn ensure_bucket(&self, bucket: &str) -> Result<(), AppError> {
+        match self.client.head_bucket().bucket(bucket).send().await {
+            Ok(_) => Ok(()),
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_not_found()) => {
+                self.create_bucket(bucket).await
+            }
+            Err(err) => Err(AppError::from_s3(err)),
+        }
+    }
+
     pub async fn delete_bucket(&self, bucket: &str) -> Result<(), AppError> {
         self.client
             .delete_bucket()
@@ -81,22 +214,360 @@ impl S3 {
         Ok(())
     }
 
-    pub async fn list_files(&self, bucket: &str) -> Result<Vec<String>, AppError> {
+    pub async fn list_buckets(&self) -> Result<Vec<BucketEntry>, AppError> {
+        let res = self
+            .client
+            .list_buckets()
+            .send()
+            .await
+            .map_err(AppError::from_s3)?;
+
+        Ok(res
+            .buckets()
+            .iter()
+            .map(|b| BucketEntry {
+                name: b.name().unwrap_or_default().to_string(),
+                created: b.creation_date().map(|d| d.secs()),
+            })
+            .collect())
+    }
+
+    pub async fn get_tags(&self, bucket: &str) -> Result<Vec<(String, String)>, AppError> {
+        match self.client.get_bucket_tagging().bucket(bucket).send().await {
+            Ok(res) => Ok(res
+                .tag_set()
+                .iter()
+                .map(|t| (t.key().to_string(), t.value().to_string()))
+                .collect()),
+            Err(err) if err.code() == Some("NoSuchTagSet") => Ok(vec![]),
+            Err(err) => Err(AppError::from_s3(err)),
+        }
+    }
+
+    pub async fn put_tags(&self, bucket: &str, tags: Vec<(String, String)>) -> Result<(), AppError> {
+        if tags.is_empty() {
+            self.client
+                .delete_bucket_tagging()
+                .bucket(bucket)
+                .send()
+                .await
+                .map_err(AppError::from_s3)?;
+            return Ok(());
+        }
+
+        let tag_set = tags
+            .into_iter()
+            .map(|(key, value)| Tag::builder().key(key).value(value).build())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let tagging = Tagging::builder()
+            .set_tag_set(Some(tag_set))
+            .build()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        self.client
+            .put_bucket_tagging()
+            .bucket(bucket)
+            .tagging(tagging)
+            .send()
+            .await
+            .map_err(AppError::from_s3)?;
+        Ok(())
+    }
+
+    /// Lists every object version and delete marker of a bucket
+    pub async fn list_all_versions(&self, bucket: &str) -> Result<Vec<ObjectRef>, AppError> {
+        let mut objects = Vec::new();
+        let mut key_marker = None;
+        let mut version_id_marker = None;
+
+        loop {
+            let res = self
+                .client
+                .list_object_versions()
+                .bucket(bucket)
+                .set_key_marker(key_marker)
+                .set_version_id_marker(version_id_marker)
+                .send()
+                .await
+                .map_err(AppError::from_s3)?;
+
+            let versions = res.versions().iter().map(|v| (v.key(), v.version_id()));
+            let markers = res.delete_markers().iter().map(|m| (m.key(), m.version_id()));
+            objects.extend(versions.chain(markers).map(|(key, version)| ObjectRef {
+                key: key.unwrap_or_default().to_string(),
+                version: version.filter(|v| *v != "null").map(|v| v.to_string()),
+            }));
+
+            if !res.is_truncated().unwrap_or_default() {
+                break;
+            }
+            key_marker = res.next_key_marker().map(|m| m.to_string());
+            version_id_marker = res.next_version_id_marker().map(|m| m.to_string());
+        }
+
+        Ok(objects)
+    }
+
+    /// Deletes objects in batches of 1000, the maximum of a single request
+    pub async fn delete_objects(&self, bucket: &str, objects: &[ObjectRef]) -> Result<(), AppError> {
+        for chunk in objects.chunks(1000) {
+            let identifiers = chunk
+                .iter()
+                .map(|o| {
+                    ObjectIdentifier::builder()
+                        .key(&o.key)
+                        .set_version_id(o.version.clone())
+                        .build()
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            let delete = Delete::builder()
+                .set_objects(Some(identifiers))
+                .quiet(true)
+                .build()
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+
+            let res = self
+                .client
+                .delete_objects()
+                .bucket(bucket)
+                .delete(delete)
+                .send()
+                .await
+                .map_err(AppError::from_s3)?;
+
+            if let Some(error) = res.errors().first() {
+                return Err(AppError::S3Error(
+                    error.code().unwrap_or_default().to_string(),
+                    format!(
+                        "Failed to delete {}: {}",
+                        error.key().unwrap_or_default(),
+                        error.message().unwrap_or_default()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// `Enabled`, `Suspended` or `Disabled` for buckets that never had versioning
+    pub async fn get_versioning(&self, bucket: &str) -> Result<String, AppError> {
+        let res = self
+            .client
+            .get_bucket_versioning()
+            .bucket(bucket)
+            .send()
+            .await
+            .map_err(AppError::from_s3)?;
+
+        Ok(res
+            .status()
+            .map(|s| s.as_str().to_string())
+            .unwrap_or_else(|| "Disabled".to_string()))
+    }
+
+    pub async fn put_versioning(&self, bucket: &str, enabled: bool) -> Result<(), AppError> {
+        let status = match enabled {
+            true => BucketVersioningStatus::Enabled,
+            false => BucketVersioningStatus::Suspended,
+        };
+
+        self.client
+            .put_bucket_versioning()
+            .bucket(bucket)
+            .versioning_configuration(VersioningConfiguration::builder().status(status).build())
+            .send()
+            .await
+            .map_err(AppError::from_s3)?;
+        Ok(())
+    }
+
+    /// Lists a single page of objects, `cursor` is the continuation token of the previous page
+    pub async fn list_page(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        delimiter: Option<&str>,
+        limit: i32,
+        cursor: Option<&str>,
+    ) -> Result<ListPage, AppError> {
         let res = self
             .client
             .list_objects_v2()
             .bucket(bucket)
+            .set_prefix(prefix.map(|p| p.to_string()))
+            .set_delimiter(delimiter.map(|d| d.to_string()))
+            .max_keys(limit)
+            .set_continuation_token(cursor.map(|c| c.to_string()))
             .send()
             .await
             .map_err(AppError::from_s3)?;
 
-        let files = res
-            .contents()
-            .iter()
-            .filter_map(|obj| obj.key().map(|s| s.to_string()))
-            .collect();
+        Ok(ListPage {
+            objects: res
+                .contents()
+                .iter()
+                .map(|obj| ObjectEntry {
+                    key: obj.key().unwrap_or_default().to_string(),
+                    size: obj.size().unwrap_or_default(),
+                    last_modified: obj.last_modified().map(|d| d.secs()),
+                    etag: obj.e_tag().map(|e| e.to_string()),
+                })
+                .collect(),
+            prefixes: res
+                .common_prefixes()
+                .iter()
+                .filter_map(|p| p.prefix().map(|p| p.to_string()))
+                .collect(),
+            next_cursor: res.next_continuation_token().map(|t| t.to_string()),
+        })
+    }
 
-        Ok(files)
+    /// All objects below `prefix`, following the pagination
+    pub async fn list_objects(&self, bucket: &str, prefix: &str) -> Result<Vec<ObjectEntry>, AppError> {
+        let mut objects = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let page = self
+                .list_page(bucket, Some(prefix), None, 1000, cursor.as_deref())
+                .await?;
+            objects.extend(page.objects);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(objects),
+            }
+        }
+    }
+
+    /// Lists all versions of a single key, newest first
+    pub async fn list_versions(&self, bucket: &str, key: &str) -> Result<Vec<VersionEntry>, AppError> {
+        let mut versions = Vec::new();
+        let mut key_marker = None;
+        let mut version_id_marker = None;
+
+        loop {
+            let res = self
+                .client
+                .list_object_versions()
+                .bucket(bucket)
+                .prefix(key)
+                .set_key_marker(key_marker)
+                .set_version_id_marker(version_id_marker)
+                .send()
+                .await
+                .map_err(AppError::from_s3)?;
+
+            versions.extend(
+                res.versions()
+                    .iter()
+                    .filter(|v| v.key() == Some(key))
+                    .map(|v| VersionEntry {
+                        version: v.version_id().unwrap_or("null").to_string(),
+                        is_latest: v.is_latest().unwrap_or_default(),
+                        size: v.size().unwrap_or_default(),
+                        last_modified: v.last_modified().map(|d| d.secs()),
+                        etag: v.e_tag().map(|e| e.to_string()),
+                    }),
+            );
+
+            // The prefix also matches longer keys, which sort after `key`
+            let past_key = res
+                .versions()
+                .last()
+                .and_then(|v| v.key())
+                .is_some_and(|k| k > key);
+            if past_key || !res.is_truncated().unwrap_or_default() {
+                break;
+            }
+            key_marker = res.next_key_marker().map(|m| m.to_string());
+            version_id_marker = res.next_version_id_marker().map(|m| m.to_string());
+        }
+
+        Ok(versions)
+    }
+
+    /// Number of versions of each of `keys`, with a single paginated listing that covers
+    /// the range from the smallest to the largest key
+    pub async fn count_versions(
+        &self,
+        bucket: &str,
+        delimiter: Option<&str>,
+        keys: &[String],
+    ) -> Result<HashMap<String, usize>, AppError> {
+        let mut counts: HashMap<String, usize> = keys.iter().map(|k| (k.clone(), 0)).collect();
+        let (Some(first), Some(last)) = (keys.iter().min(), keys.iter().max()) else {
+            return Ok(counts);
+        };
+        // Every key between first and last shares their common prefix
+        let prefix = common_prefix(first, last);
+        // The listing starts right before `first` instead of at the beginning of the prefix
+        let mut key_marker = Some(key_before(first));
+        let mut version_id_marker = None;
+
+        loop {
+            let res = self
+                .client
+                .list_object_versions()
+                .bucket(bucket)
+                .prefix(prefix)
+                .set_delimiter(delimiter.map(|d| d.to_string()))
+                .set_key_marker(key_marker)
+                .set_version_id_marker(version_id_marker)
+                .send()
+                .await
+                .map_err(AppError::from_s3)?;
+
+            for key in res.versions().iter().filter_map(|v| v.key()) {
+                if let Some(count) = counts.get_mut(key) {
+                    *count += 1;
+                }
+            }
+
+            let past_last = res
+                .versions()
+                .last()
+                .and_then(|v| v.key())
+                .is_some_and(|key| key > last.as_str());
+            if past_last || !res.is_truncated().unwrap_or_default() {
+                break;
+            }
+            key_marker = res.next_key_marker().map(|m| m.to_string());
+            version_id_marker = res.next_version_id_marker().map(|m| m.to_string());
+        }
+
+        Ok(counts)
+    }
+
+    /// Lists all keys below a prefix, following continuation tokens
+    pub async fn list_keys(&self, bucket: &str, prefix: &str) -> Result<Vec<String>, AppError> {
+        let mut keys = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let res = self
+                .client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(AppError::from_s3)?;
+
+            keys.extend(
+                res.contents()
+                    .iter()
+                    .filter_map(|obj| obj.key().map(|s| s.to_string())),
+            );
+
+            match res.next_continuation_token() {
+                Some(token) => continuation_token = Some(token.to_string()),
+                None => break,
+            }
+        }
+
+        Ok(keys)
     }
 
     pub async fn upload_file(
@@ -104,24 +575,134 @@ impl S3 {
         bucket: &str,
         key: &str,
         body: ByteStream,
-    ) -> Result<(), AppError> {
-        self.client
+        meta: &ObjectMeta,
+    ) -> Result<PutResult, AppError> {
+        let res = self
+            .client
             .put_object()
             .bucket(bucket)
             .key(key)
+            .set_content_type(meta.content_type.clone())
+            .set_metadata(Some(meta.to_s3()))
             .body(body)
             .send()
             .await
             .map_err(AppError::from_s3)?;
+        Ok(PutResult {
+            version: res.version_id().map(|v| v.to_string()),
+            etag: res.e_tag().map(|e| e.to_string()),
+        })
+    }
+
+    pub async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        meta: &ObjectMeta,
+    ) -> Result<String, AppError> {
+        let res = self
+            .client
+            .create_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .set_content_type(meta.content_type.clone())
+            .set_metadata(Some(meta.to_s3()))
+            .send()
+            .await
+            .map_err(AppError::from_s3)?;
+
+        res.upload_id()
+            .map(|id| id.to_string())
+            .ok_or_else(|| AppError::Internal("Missing upload id".to_string()))
+    }
+
+    pub async fn upload_part(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        body: ByteStream,
+    ) -> Result<CompletedPart, AppError> {
+        let res = self
+            .client
+            .upload_part()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(body)
+            .send()
+            .await
+            .map_err(AppError::from_s3)?;
+
+        Ok(CompletedPart::builder()
+            .part_number(part_number)
+            .set_e_tag(res.e_tag().map(|e| e.to_string()))
+            .build())
+    }
+
+    pub async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<CompletedPart>,
+    ) -> Result<PutResult, AppError> {
+        let res = self
+            .client
+            .complete_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(AppError::from_s3)?;
+
+        Ok(PutResult {
+            version: res.version_id().map(|v| v.to_string()),
+            etag: res.e_tag().map(|e| e.to_string()),
+        })
+    }
+
+    pub async fn abort_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> Result<(), AppError> {
+        self.client
+            .abort_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .send()
+            .await
+            .map_err(AppError::from_s3)?;
         Ok(())
     }
 
     pub async fn download_file(&self, bucket: &str, key: &str) -> Result<ByteStream, AppError> {
+        self.download_file_version(bucket, key, None).await
+    }
+
+    pub async fn download_file_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<ByteStream, AppError> {
         let res = self
             .client
             .get_object()
             .bucket(bucket)
             .key(key)
+            .set_version_id(version_id.map(|v| v.to_string()))
             .send()
             .await
             .map_err(AppError::from_s3)?;
@@ -129,6 +710,267 @@ impl S3 {
         Ok(res.body)
     }
 
+    /// Reads a whole object into memory, meant for small objects and tests
+    pub async fn download_bytes(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<u8>, AppError> {
+        Ok(self
+            .download_file_version(bucket, key, version_id)
+            .await?
+            .collect()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .to_vec())
+    }
+
+    /// Reads a whole object of at most `max_size` bytes, `None` when it is larger.
+    /// The limit applies to the bytes received, not to a previously read size
+    pub async fn download_limited(
+        &self,
+        bucket: &str,
+        key: &str,
+        max_size: usize,
+    ) -> Result<Option<Vec<u8>>, AppError> {
+        let mut body = self.download_file_version(bucket, key, None).await?;
+        let mut data = Vec::new();
+        while let Some(chunk) = body
+            .try_next()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+        {
+            if data.len() + chunk.len() > max_size {
+                return Ok(None);
+            }
+            data.extend_from_slice(&chunk);
+        }
+        Ok(Some(data))
+    }
+
+    /// Reads a JSON document, `None` when the key does not exist
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<T>, AppError> {
+        let res = match self.client.get_object().bucket(bucket).key(key).send().await {
+            Ok(res) => res,
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_no_such_key()) => {
+                return Ok(None);
+            }
+            Err(err) => return Err(AppError::from_s3(err)),
+        };
+
+        let bytes = res
+            .body
+            .collect()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .into_bytes();
+
+        let value = serde_json::from_slice(&bytes).map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(Some(value))
+    }
+
+    pub async fn put_json<T: Serialize>(
+        &self,
+        bucket: &str,
+        key: &str,
+        value: &T,
+    ) -> Result<(), AppError> {
+        let bytes = serde_json::to_vec(value).map_err(|e| AppError::Internal(e.to_string()))?;
+        self.client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .content_type("application/json")
+            .body(ByteStream::from(bytes))
+            .send()
+            .await
+            .map_err(AppError::from_s3)?;
+        Ok(())
+    }
+
+    /// Copies an older version on top of the key, making it the current version
+    pub async fn restore_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<PutResult, AppError> {
+        let res = self
+            .client
+            .copy_object()
+            .bucket(bucket)
+            .key(key)
+            .copy_source(format!("{}/{}?versionId={}", bucket, encode_key(key), version_id))
+            .send()
+            .await
+            .map_err(AppError::from_s3)?;
+
+        Ok(PutResult {
+            version: res.version_id().map(|v| v.to_string()),
+            etag: res
+                .copy_object_result()
+                .and_then(|r| r.e_tag())
+                .map(|e| e.to_string()),
+        })
+    }
+
+    /// Headers of an object, `None` when `if_none_match` matches the current ETag
+    pub async fn head_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        options: &GetOptions<'_>,
+    ) -> Result<Option<ObjectInfo>, AppError> {
+        let res = self
+            .client
+            .head_object()
+            .bucket(bucket)
+            .key(key)
+            .set_version_id(options.version.map(|v| v.to_string()))
+            .set_if_none_match(options.if_none_match.map(|v| v.to_string()))
+            .send()
+            .await;
+
+        let res = match res {
+            Ok(res) => res,
+            Err(err) if status_of(&err) == Some(304) => return Ok(None),
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_not_found()) => {
+                return Err(AppError::NotFound(format!("{} not found in {}", key, bucket)));
+            }
+            // HEAD answers carry no error code, a malformed version id is a plain 400
+            Err(err) if status_of(&err) == Some(400) => {
+                return Err(AppError::BadRequest(format!("Invalid request for {} in {}", key, bucket)));
+            }
+            Err(err) => return Err(AppError::from_s3(err)),
+        };
+
+        Ok(Some(ObjectInfo {
+            size: res.content_length().unwrap_or_default(),
+            etag: res.e_tag().map(|e| e.to_string()),
+            last_modified: res.last_modified().and_then(http_date),
+            version: res.version_id().map(|v| v.to_string()),
+            content_range: None,
+            meta: ObjectMeta::from_s3(res.content_type(), res.metadata()),
+        }))
+    }
+
+    /// Conditional and ranged download, `None` when `if_none_match` matches the current ETag
+    pub async fn get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        options: &GetOptions<'_>,
+    ) -> Result<Option<(ObjectInfo, ByteStream)>, AppError> {
+        let res = self
+            .client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .set_version_id(options.version.map(|v| v.to_string()))
+            .set_range(options.range.map(|v| v.to_string()))
+            .set_if_none_match(options.if_none_match.map(|v| v.to_string()))
+            .send()
+            .await;
+
+        let res = match res {
+            Ok(res) => res,
+            Err(err) if status_of(&err) == Some(304) => return Ok(None),
+            Err(err) if status_of(&err) == Some(416) => {
+                return Err(AppError::RangeNotSatisfiable(format!(
+                    "Range {} is outside of {}",
+                    options.range.unwrap_or_default(),
+                    key
+                )));
+            }
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_no_such_key()) => {
+                return Err(AppError::NotFound(format!("{} not found in {}", key, bucket)));
+            }
+            Err(err) => return Err(AppError::from_s3(err)),
+        };
+
+        let info = ObjectInfo {
+            size: res.content_length().unwrap_or_default(),
+            etag: res.e_tag().map(|e| e.to_string()),
+            last_modified: res.last_modified().and_then(http_date),
+            version: res.version_id().map(|v| v.to_string()),
+            content_range: res.content_range().map(|r| r.to_string()),
+            meta: ObjectMeta::from_s3(res.content_type(), res.metadata()),
+        };
+        Ok(Some((info, res.body)))
+    }
+
+    /// The content length is signed, the URL only accepts a body of exactly `size` bytes
+    pub async fn presign_put(
+        &self,
+        bucket: &str,
+        key: &str,
+        size: i64,
+        expires_in: Duration,
+    ) -> Result<PresignedRequest, AppError> {
+        let res = self
+            .client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .content_length(size)
+            .presigned(presigning_config(expires_in)?)
+            .await
+            .map_err(AppError::from_s3)?;
+
+        Ok(presigned_request(&res))
+    }
+
+    pub async fn presign_get(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+        expires_in: Duration,
+    ) -> Result<PresignedRequest, AppError> {
+        let res = self
+            .client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .set_version_id(version_id.map(|v| v.to_string()))
+            .presigned(presigning_config(expires_in)?)
+            .await
+            .map_err(AppError::from_s3)?;
+
+        Ok(presigned_request(&res))
+    }
+
+    pub async fn delete_version(&self, bucket: &str, key: &str, version_id: &str) -> Result<(), AppError> {
+        self.client
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .version_id(version_id)
+            .send()
+            .await
+            .map_err(AppError::from_s3)?;
+        Ok(())
+    }
+
+    /// Deletes every version of `key`, a versioned bucket keeps nothing behind
+    pub async fn purge(&self, bucket: &str, key: &str) -> Result<(), AppError> {
+        let objects: Vec<ObjectRef> = self
+            .list_versions(bucket, key)
+            .await?
+            .into_iter()
+            .map(|v| ObjectRef {
+                key: key.to_string(),
+                version: Some(v.version).filter(|v| v != "null"),
+            })
+            .collect();
+        self.delete_objects(bucket, &objects).await
+    }
+
     pub async fn delete_file(&self, bucket: &str, key: &str) -> Result<(), AppError> {
         self.client
             .delete_object()
@@ -139,4 +981,85 @@ impl S3 {
             .map_err(AppError::from_s3)?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+fn status_of<E>(err: &SdkError<E, HttpResponse>) -> Option<u16> {
+    err.raw_response().map(|r| r.status().as_u16())
+}
+
+fn http_date(date: &DateTime) -> Option<String> {
+    date.fmt(DateTimeFormat::HttpDate).ok()
+}
+
+fn presigning_config(expires_in: Duration) -> Result<PresigningConfig, AppError> {
+    PresigningConfig::expires_in(expires_in).map_err(|e| AppError::BadRequest(e.to_string()))
+}
+
+fn presigned_request(req: &PresignedRequestSdk) -> PresignedRequest {
+    PresignedRequest {
+        method: req.method().to_string(),
+        url: req.uri().to_string(),
+        headers: req
+            .headers()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    }
+}
+
+/// Longest common prefix of two keys, cut at a char boundary
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let end = a
+        .char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map_or(a.len().min(b.len()), |((i, _), _)| i);
+    &a[..end]
+}
+
+/// Key sorting right before `key`, a listing that starts after it still includes `key`
+pub fn key_before(key: &str) -> String {
+    let mut before = key.to_string();
+    let previous = before
+        .pop()
+        .and_then(|last| char::from_u32((last as u32).checked_sub(1)?));
+    before.extend(previous);
+    before
+}
+
+/// Percent-encodes a key for use in the `x-amz-copy-source` header, `/` is kept
+pub fn encode_key(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Reverses `encode_key`, invalid escapes are kept as they are
+pub fn decode_key(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
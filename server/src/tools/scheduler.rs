@@ -0,0 +1,354 @@
+use crate::error::AppError;
+use crate::tools::invoke::Invoker;
+use crate::tools::jobs::{system_bucket, JobError};
+use crate::tools::s3::S3;
+use crate::tools::store::Store;
+use crate::tools::{names, unix_now};
+use chrono::TimeZone;
+use chrono_tz::Tz;
+use cron::Schedule as CronSchedule;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// A run starting later than this after its time counts as missed
+const GRACE: u64 = 60;
+
+/// Missed runs older than this are not caught up
+const CATCH_UP_WINDOW: u64 = 24 * 60 * 60;
+
+/// Runs kept in the history of a schedule
+const HISTORY_SIZE: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum MissedRuns {
+    /// Missed runs are recorded as skipped, the schedule continues with the next time
+    #[default]
+    Skip,
+    /// Missed runs of the last 24 hours are executed one after another
+    CatchUp,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Schedule {
+    pub name: String,
+    /// Cron expression, 5 fields (minute precision) or 6-7 fields starting with seconds
+    pub cron: String,
+    pub timezone: String,
+    pub bucket: String,
+    /// Function key, may name an alias as `key@alias`
+    pub key: String,
+    pub input: serde_json::Value,
+    pub missed: MissedRuns,
+    pub enabled: bool,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub next_run: Option<u64>,
+    pub last_run: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduleRun {
+    pub scheduled_at: u64,
+    pub started_at: Option<u64>,
+    pub status: RunStatus,
+    pub version: Option<String>,
+    pub error: Option<JobError>,
+    pub duration_ms: u64,
+    /// Why a run was skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ScheduleRun {
+    fn skipped(scheduled_at: u64, reason: &str) -> ScheduleRun {
+        ScheduleRun {
+            scheduled_at,
+            started_at: None,
+            status: RunStatus::Skipped,
+            version: None,
+            error: None,
+            duration_ms: 0,
+            reason: Some(reason.to_string()),
+        }
+    }
+}
+
+/// Runs functions on cron schedules through the same path as `exec_wasm`.
+///
+/// Schedules and their run history are persisted in the system bucket, the next
+/// run time survives restarts so missed runs are detected.
+pub struct Scheduler {
+    invoker: Arc<Invoker>,
+    definitions: Store,
+    histories: Store,
+    schedules: Mutex<HashMap<String, Schedule>>,
+    running: Mutex<HashSet<String>>,
+    history: tokio::sync::Mutex<()>,
+}
+
+impl Scheduler {
+    pub async fn start(s3: Arc<S3>, invoker: Arc<Invoker>) -> Result<Arc<Scheduler>, AppError> {
+        s3.ensure_bucket(&system_bucket()).await?;
+        let definitions = Store::new(s3.clone(), "schedules");
+        let histories = Store::new(s3, "schedule-runs");
+
+        let schedules = definitions
+            .list::<Schedule>()
+            .await?
+            .into_iter()
+            .map(|schedule| (schedule.name.clone(), schedule))
+            .collect();
+
+        let scheduler = Arc::new(Scheduler {
+            invoker,
+            definitions,
+            histories,
+            schedules: Mutex::new(schedules),
+            running: Mutex::new(HashSet::new()),
+            history: tokio::sync::Mutex::new(()),
+        });
+
+        let ticker = scheduler.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TICK);
+            loop {
+                interval.tick().await;
+                ticker.tick().await;
+            }
+        });
+
+        Ok(scheduler)
+    }
+
+    pub fn list(&self) -> Vec<Schedule> {
+        let mut schedules: Vec<Schedule> = self.schedules.lock().unwrap().values().cloned().collect();
+        schedules.sort_by(|a, b| a.name.cmp(&b.name));
+        schedules
+    }
+
+    pub fn get(&self, name: &str) -> Result<Schedule, AppError> {
+        self.schedules
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Schedule {} not found", name)))
+    }
+
+    /// Creates or replaces a schedule, the next run is computed from now
+    pub async fn put(&self, mut schedule: Schedule) -> Result<Schedule, AppError> {
+        names::validate_name("schedule", &schedule.name)?;
+        let cron = parse_cron(&schedule.cron)?;
+        let timezone = parse_timezone(&schedule.timezone)?;
+
+        let now = unix_now();
+        if let Ok(existing) = self.get(&schedule.name) {
+            schedule.created_at = existing.created_at;
+            schedule.last_run = existing.last_run;
+        }
+        schedule.updated_at = now;
+        schedule.next_run = next_after(&cron, timezone, now);
+
+        self.save(&schedule).await?;
+        self.schedules
+            .lock()
+            .unwrap()
+            .insert(schedule.name.clone(), schedule.clone());
+        Ok(schedule)
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<(), AppError> {
+        self.get(name)?;
+        // Under the history lock, a run finishing now either recorded already or sees the deletion
+        let _guard = self.history.lock().await;
+        self.schedules.lock().unwrap().remove(name);
+        self.definitions.delete(name).await?;
+        self.histories.delete(name).await?;
+        Ok(())
+    }
+
+    /// Latest runs first
+    pub async fn runs(&self, name: &str) -> Result<Vec<ScheduleRun>, AppError> {
+        self.get(name)?;
+        let mut runs: Vec<ScheduleRun> = self.histories.get(name).await?.unwrap_or_default();
+        runs.reverse();
+        Ok(runs)
+    }
+
+    async fn tick(self: &Arc<Self>) {
+        let now = unix_now();
+        let due: Vec<Schedule> = self
+            .schedules
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|s| s.enabled && s.next_run.is_some_and(|t| t <= now))
+            .cloned()
+            .collect();
+
+        for schedule in due {
+            if let Err(e) = self.fire(schedule, now).await {
+                tracing::warn!("Failed to run schedule: {}", e);
+            }
+        }
+    }
+
+    async fn fire(self: &Arc<Self>, schedule: Schedule, now: u64) -> Result<(), AppError> {
+        let Some(scheduled_at) = schedule.next_run else {
+            return Ok(());
+        };
+        let cron = parse_cron(&schedule.cron)?;
+        let timezone = parse_timezone(&schedule.timezone)?;
+        let late = now.saturating_sub(scheduled_at) > GRACE;
+        let running = self.running.lock().unwrap().contains(&schedule.name);
+
+        match schedule.missed {
+            // Catch-up runs wait for the previous one instead of overlapping it
+            MissedRuns::CatchUp if running => return Ok(()),
+            MissedRuns::CatchUp => {
+                let window_start = now.saturating_sub(CATCH_UP_WINDOW);
+                let from = scheduled_at.max(window_start);
+                self.advance(&schedule.name, next_after(&cron, timezone, from)).await?;
+                // Older runs are dropped, catching up continues with the first one in the window
+                if scheduled_at < window_start {
+                    return self
+                        .record(
+                            &schedule.name,
+                            ScheduleRun::skipped(scheduled_at, "older than the catch-up window"),
+                        )
+                        .await;
+                }
+            }
+            MissedRuns::Skip => {
+                self.advance(&schedule.name, next_after(&cron, timezone, now)).await?;
+                if late {
+                    return self
+                        .record(&schedule.name, ScheduleRun::skipped(scheduled_at, "missed"))
+                        .await;
+                }
+                if running {
+                    return self
+                        .record(
+                            &schedule.name,
+                            ScheduleRun::skipped(scheduled_at, "previous run still running"),
+                        )
+                        .await;
+                }
+            }
+        }
+
+        self.running.lock().unwrap().insert(schedule.name.clone());
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            let run = scheduler.execute(&schedule, scheduled_at).await;
+            scheduler.running.lock().unwrap().remove(&schedule.name);
+            if let Err(e) = scheduler.record(&schedule.name, run).await {
+                tracing::warn!("Failed to record run of schedule {}: {}", schedule.name, e);
+            }
+        });
+        Ok(())
+    }
+
+    async fn execute(&self, schedule: &Schedule, scheduled_at: u64) -> ScheduleRun {
+        let started_at = unix_now();
+        let start = Instant::now();
+        let result = self
+            .invoker
+            .invoke(&schedule.bucket, &schedule.key, None, &schedule.input)
+            .await;
+
+        let (status, version, error) = match result {
+            Ok(invocation) => (RunStatus::Succeeded, invocation.version, None),
+            Err(e) => (RunStatus::Failed, None, Some(JobError::from(&e))),
+        };
+        ScheduleRun {
+            scheduled_at,
+            started_at: Some(started_at),
+            status,
+            version,
+            error,
+            duration_ms: start.elapsed().as_millis() as u64,
+            reason: None,
+        }
+    }
+
+    async fn advance(&self, name: &str, next_run: Option<u64>) -> Result<(), AppError> {
+        let schedule = {
+            let mut schedules = self.schedules.lock().unwrap();
+            let Some(schedule) = schedules.get_mut(name) else {
+                return Ok(());
+            };
+            schedule.next_run = next_run;
+            schedule.clone()
+        };
+        self.save(&schedule).await
+    }
+
+    async fn record(&self, name: &str, run: ScheduleRun) -> Result<(), AppError> {
+        let _guard = self.history.lock().await;
+
+        let schedule = {
+            let mut schedules = self.schedules.lock().unwrap();
+            schedules.get_mut(name).map(|s| {
+                if let Some(started_at) = run.started_at {
+                    s.last_run = Some(started_at);
+                }
+                s.clone()
+            })
+        };
+        // Deleted while the run was in flight, its history is gone as well
+        let Some(schedule) = schedule else {
+            return Ok(());
+        };
+        if run.started_at.is_some() {
+            self.save(&schedule).await?;
+        }
+
+        let mut runs: Vec<ScheduleRun> = self.histories.get(name).await?.unwrap_or_default();
+        runs.push(run);
+        if runs.len() > HISTORY_SIZE {
+            runs.drain(..runs.len() - HISTORY_SIZE);
+        }
+        self.histories.put(name, &runs).await
+    }
+
+    async fn save(&self, schedule: &Schedule) -> Result<(), AppError> {
+        self.definitions.put(&schedule.name, schedule).await
+    }
+}
+
+/// Accepts the common 5 field syntax by adding the seconds field
+pub fn parse_cron(expression: &str) -> Result<CronSchedule, AppError> {
+    let expression = match expression.split_whitespace().count() {
+        5 => format!("0 {}", expression.trim()),
+        _ => expression.trim().to_string(),
+    };
+    CronSchedule::from_str(&expression)
+        .map_err(|e| AppError::BadRequest(format!("Invalid cron expression {}: {}", expression, e)))
+}
+
+pub fn parse_timezone(timezone: &str) -> Result<Tz, AppError> {
+    timezone
+        .parse()
+        .map_err(|_| AppError::BadRequest(format!("Unknown timezone {}", timezone)))
+}
+
+/// First time of the schedule after the unix timestamp `after`
+pub fn next_after(cron: &CronSchedule, timezone: Tz, after: u64) -> Option<u64> {
+    let after = timezone.timestamp_opt(after as i64, 0).single()?;
+    cron.after(&after).next().map(|t| t.timestamp() as u64)
+}
@@ -0,0 +1,83 @@
+use crate::error::AppError;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use serde::{Deserialize, Serialize};
+
+const NONCE_SIZE: usize = 12;
+
+/// Secret value encrypted with AES-256-GCM
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Sealed {
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// Encrypts secrets at rest with the server master key
+pub struct Secrets {
+    cipher: Option<Aes256Gcm>,
+}
+
+impl Secrets {
+    /// Reads the hex encoded 256 bit key in `FAAS_MASTER_KEY`, without it secrets can't be used
+    pub fn from_env() -> Result<Secrets, AppError> {
+        let Ok(value) = std::env::var("FAAS_MASTER_KEY") else {
+            return Ok(Secrets { cipher: None });
+        };
+        let bytes = hex::decode(value.trim())
+            .ok()
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or_else(|| AppError::Internal("FAAS_MASTER_KEY has to be 64 hex digits".to_string()))?;
+
+        Ok(Secrets::with_key(&bytes))
+    }
+
+    pub fn with_key(key: &[u8]) -> Secrets {
+        Secrets {
+            cipher: Some(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))),
+        }
+    }
+
+    fn cipher(&self) -> Result<&Aes256Gcm, AppError> {
+        self.cipher
+            .as_ref()
+            .ok_or_else(|| AppError::Unavailable("Secrets need FAAS_MASTER_KEY to be set".to_string()))
+    }
+
+    /// `context` is authenticated with the value, a sealed secret can't be moved to another name
+    pub fn seal(&self, context: &str, value: &str) -> Result<Sealed, AppError> {
+        let nonce: [u8; NONCE_SIZE] = rand::random();
+        let payload = Payload {
+            msg: value.as_bytes(),
+            aad: context.as_bytes(),
+        };
+        let ciphertext = self
+            .cipher()?
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|_| AppError::Internal("Failed to encrypt secret".to_string()))?;
+
+        Ok(Sealed {
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    pub fn open(&self, context: &str, sealed: &Sealed) -> Result<String, AppError> {
+        // Never mention the value, errors end up in logs and job records
+        let failed = || AppError::Internal(format!("Failed to decrypt secret {}", context));
+
+        let nonce = hex::decode(&sealed.nonce)
+            .ok()
+            .filter(|nonce| nonce.len() == NONCE_SIZE)
+            .ok_or_else(failed)?;
+        let ciphertext = hex::decode(&sealed.ciphertext).map_err(|_| failed())?;
+        let payload = Payload {
+            msg: &ciphertext,
+            aad: context.as_bytes(),
+        };
+        let plaintext = self
+            .cipher()?
+            .decrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|_| failed())?;
+        String::from_utf8(plaintext).map_err(|_| failed())
+    }
+}
@@ -0,0 +1,78 @@
+use crate::error::AppError;
+use crate::tools::registry::FunctionVersion;
+use ed25519_dalek::{Signature, VerifyingKey};
+
+/// Checks the detached ed25519 signatures of deployed functions.
+///
+/// A signature covers the lowercase hex sha256 digest of the stored component,
+/// keys are configured as hex encoded public keys in `FAAS_TRUSTED_KEYS`. Digests and
+/// signatures are kept in the bucket of the function, so they detect corruption and
+/// unsigned code but not a writer replacing a component with another signed one.
+pub struct Verifier {
+    keys: Vec<VerifyingKey>,
+    pub required: bool,
+}
+
+impl Verifier {
+    pub fn from_env() -> Result<Verifier, AppError> {
+        let keys = std::env::var("FAAS_TRUSTED_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(parse_key)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let required = std::env::var("FAAS_REQUIRE_SIGNATURE")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        Ok(Verifier { keys, required })
+    }
+
+    pub fn verify(&self, sha256: &str, signature: Option<&str>) -> Result<(), AppError> {
+        let Some(signature) = signature else {
+            return match self.required {
+                true => Err(AppError::Forbidden("Function is not signed".to_string())),
+                false => Ok(()),
+            };
+        };
+
+        let signature = hex::decode(signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(|| AppError::Forbidden("Malformed signature".to_string()))?;
+
+        if self
+            .keys
+            .iter()
+            .any(|key| key.verify_strict(sha256.as_bytes(), &signature).is_ok())
+        {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden("Signature does not match any trusted key".to_string()))
+        }
+    }
+
+    /// Provenance check before an execution, `None` when no digest was recorded on upload
+    pub fn verify_function(&self, key: &str, recorded: Option<&FunctionVersion>) -> Result<(), AppError> {
+        match recorded {
+            Some(version) => self.verify(&version.sha256, version.signature.as_deref()),
+            None if self.required => Err(AppError::Forbidden(format!(
+                "No digest recorded for {}, upload it again through the API",
+                key
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_key(value: &str) -> Result<VerifyingKey, AppError> {
+    let bytes: [u8; 32] = hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| AppError::Internal(format!("Invalid trusted key {}", value)))?;
+
+    VerifyingKey::from_bytes(&bytes)
+        .map_err(|e| AppError::Internal(format!("Invalid trusted key {}: {}", value, e)))
+}
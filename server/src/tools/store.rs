@@ -0,0 +1,79 @@
+use crate::error::AppError;
+use crate::tools::jobs::system_bucket;
+use crate::tools::s3::{S3, INTERNAL_PREFIX};
+use crate::tools::unix_now;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// JSON documents of one kind in the system bucket, stored as `.faas/{kind}/{name}.json`
+#[derive(Clone)]
+pub struct Store {
+    s3: Arc<S3>,
+    bucket: String,
+    prefix: String,
+}
+
+impl Store {
+    pub fn new(s3: Arc<S3>, kind: &str) -> Store {
+        Store {
+            s3,
+            bucket: system_bucket(),
+            prefix: format!("{}{}/", INTERNAL_PREFIX, kind),
+        }
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}{}.json", self.prefix, name)
+    }
+
+    pub async fn list<T: DeserializeOwned>(&self) -> Result<Vec<T>, AppError> {
+        let mut documents = Vec::new();
+        for key in self.s3.list_keys(&self.bucket, &self.prefix).await? {
+            if let Some(document) = self.s3.get_json(&self.bucket, &key).await? {
+                documents.push(document);
+            }
+        }
+        Ok(documents)
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>, AppError> {
+        self.s3.get_json(&self.bucket, &self.key(name)).await
+    }
+
+    pub async fn put<T: Serialize>(&self, name: &str, document: &T) -> Result<(), AppError> {
+        self.s3.put_json(&self.bucket, &self.key(name), document).await
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<(), AppError> {
+        self.s3.delete_file(&self.bucket, &self.key(name)).await
+    }
+
+    /// Removes the documents `expired` holds for at the time of the sweep, every 5 minutes
+    pub fn start_sweep<T: DeserializeOwned + 'static>(&self, expired: fn(&T, u64) -> bool) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = store.sweep(expired).await {
+                    tracing::warn!("Failed to sweep expired {}: {}", store.prefix, e);
+                }
+            }
+        });
+    }
+
+    async fn sweep<T: DeserializeOwned>(&self, expired: fn(&T, u64) -> bool) -> Result<(), AppError> {
+        let now = unix_now();
+        for key in self.s3.list_keys(&self.bucket, &self.prefix).await? {
+            let document = self.s3.get_json::<T>(&self.bucket, &key).await?;
+            if document.is_some_and(|document| expired(&document, now)) {
+                self.s3.delete_file(&self.bucket, &key).await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+use crate::error::AppError;
+use crate::tools::jobs::{system_bucket, Jobs};
+use crate::tools::s3::{decode_key, S3, INTERNAL_PREFIX};
+use crate::tools::store::Store;
+use crate::tools::{names, unix_now};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// Shortest polling interval, listing a large prefix is not free
+const MIN_POLL_INTERVAL: u64 = 5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EventType {
+    Created,
+    Deleted,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TriggerSource {
+    /// S3 bucket notifications posted to `/events/s3`
+    Notification,
+    /// `list_objects_v2` results compared every `poll_interval` seconds
+    Poll,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Trigger {
+    pub name: String,
+    /// Watched bucket and prefix
+    pub bucket: String,
+    pub prefix: String,
+    pub events: Vec<EventType>,
+    pub source: TriggerSource,
+    pub poll_interval: u64,
+    /// Function executed for every event, `target_key` may name an alias
+    pub target_bucket: String,
+    pub target_key: String,
+    pub enabled: bool,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl Trigger {
+    fn matches(&self, event: &ObjectEvent) -> bool {
+        self.enabled
+            && self.bucket == event.bucket
+            && event.key.starts_with(&self.prefix)
+            && self.events.contains(&event.event)
+    }
+}
+
+/// Input of a triggered function
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ObjectEvent {
+    pub event: EventType,
+    pub bucket: String,
+    pub key: String,
+    pub size: Option<i64>,
+    pub etag: Option<String>,
+    pub source: TriggerSource,
+    pub time: u64,
+    /// Name of the trigger, set when the event is dispatched
+    #[serde(default)]
+    pub trigger: String,
+}
+
+/// Size and ETag of the objects seen by the last poll
+pub type Snapshot = HashMap<String, (i64, Option<String>)>;
+
+/// Runs functions as jobs when objects are created or deleted in a watched bucket/prefix.
+///
+/// Triggers and the snapshots of polled prefixes are persisted in the system bucket,
+/// changes made while the server was down are picked up by the next poll.
+pub struct Triggers {
+    s3: Arc<S3>,
+    jobs: Arc<Jobs>,
+    bucket: String,
+    definitions: Store,
+    snapshots: Store,
+    triggers: Mutex<HashMap<String, Trigger>>,
+    next_poll: Mutex<HashMap<String, u64>>,
+}
+
+impl Triggers {
+    pub async fn start(s3: Arc<S3>, jobs: Arc<Jobs>) -> Result<Arc<Triggers>, AppError> {
+        let bucket = system_bucket();
+        s3.ensure_bucket(&bucket).await?;
+
+        let definitions = Store::new(s3.clone(), "triggers");
+        let snapshots = Store::new(s3.clone(), "trigger-snapshots");
+        let triggers = definitions
+            .list::<Trigger>()
+            .await?
+            .into_iter()
+            .map(|trigger| (trigger.name.clone(), trigger))
+            .collect();
+
+        let watcher = Arc::new(Triggers {
+            s3,
+            jobs,
+            bucket,
+            definitions,
+            snapshots,
+            triggers: Mutex::new(triggers),
+            next_poll: Mutex::new(HashMap::new()),
+        });
+
+        let poller = watcher.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TICK);
+            loop {
+                interval.tick().await;
+                poller.poll_due().await;
+            }
+        });
+
+        Ok(watcher)
+    }
+
+    pub fn list(&self) -> Vec<Trigger> {
+        let mut triggers: Vec<Trigger> = self.triggers.lock().unwrap().values().cloned().collect();
+        triggers.sort_by(|a, b| a.name.cmp(&b.name));
+        triggers
+    }
+
+    pub fn get(&self, name: &str) -> Result<Trigger, AppError> {
+        self.triggers
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Trigger {} not found", name)))
+    }
+
+    /// Creates or replaces a trigger, a changed bucket or prefix starts with a new baseline
+    pub async fn put(&self, mut trigger: Trigger) -> Result<Trigger, AppError> {
+        names::validate_name("trigger", &trigger.name)?;
+        names::validate_bucket(&trigger.bucket)?;
+        if trigger.bucket == self.bucket || trigger.prefix.starts_with(INTERNAL_PREFIX) {
+            return Err(AppError::BadRequest(
+                "The server's own objects can't be watched".to_string(),
+            ));
+        }
+        if trigger.events.is_empty() {
+            return Err(AppError::BadRequest("At least one event type is required".to_string()));
+        }
+        trigger.poll_interval = trigger.poll_interval.max(MIN_POLL_INTERVAL);
+        trigger.updated_at = unix_now();
+
+        if let Ok(existing) = self.get(&trigger.name) {
+            trigger.created_at = existing.created_at;
+            if existing.bucket != trigger.bucket || existing.prefix != trigger.prefix {
+                self.snapshots.delete(&trigger.name).await?;
+            }
+        }
+
+        self.definitions.put(&trigger.name, &trigger).await?;
+        self.next_poll.lock().unwrap().remove(&trigger.name);
+        self.triggers
+            .lock()
+            .unwrap()
+            .insert(trigger.name.clone(), trigger.clone());
+        Ok(trigger)
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<(), AppError> {
+        self.get(name)?;
+        self.definitions.delete(name).await?;
+        self.snapshots.delete(name).await?;
+        self.triggers.lock().unwrap().remove(name);
+        self.next_poll.lock().unwrap().remove(name);
+        Ok(())
+    }
+
+    /// Queues a job for every notification trigger matching the event, returns the job ids
+    pub async fn dispatch(&self, event: ObjectEvent) -> Result<Vec<String>, AppError> {
+        let matching: Vec<Trigger> = self
+            .triggers
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|t| t.source == event.source && t.matches(&event))
+            .cloned()
+            .collect();
+
+        let mut ids = Vec::new();
+        for trigger in matching {
+            ids.push(self.submit(&trigger, event.clone()).await?);
+        }
+        Ok(ids)
+    }
+
+    async fn submit(&self, trigger: &Trigger, mut event: ObjectEvent) -> Result<String, AppError> {
+        event.trigger = trigger.name.clone();
+        let input = serde_json::to_value(&event).map_err(|e| AppError::Internal(e.to_string()))?;
+        let job = self
+            .jobs
+            .submit(&trigger.target_bucket, &trigger.target_key, None, input, None, None)
+            .await?;
+        Ok(job.id)
+    }
+
+    async fn poll_due(&self) {
+        let now = unix_now();
+        let due: Vec<Trigger> = {
+            let next_poll = self.next_poll.lock().unwrap();
+            self.triggers
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|t| t.enabled && t.source == TriggerSource::Poll)
+                .filter(|t| next_poll.get(&t.name).is_none_or(|next| *next <= now))
+                .cloned()
+                .collect()
+        };
+
+        for trigger in due {
+            self.next_poll
+                .lock()
+                .unwrap()
+                .insert(trigger.name.clone(), now + trigger.poll_interval);
+            if let Err(e) = self.poll(&trigger).await {
+                tracing::warn!("Failed to poll trigger {}: {}", trigger.name, e);
+            }
+        }
+    }
+
+    /// Compares the listing with the last snapshot, the first poll only records the baseline
+    async fn poll(&self, trigger: &Trigger) -> Result<(), AppError> {
+        let objects = self.s3.list_objects(&trigger.bucket, &trigger.prefix).await?;
+        // Registry, alias, config and upload writes of the server are not events
+        let current: Snapshot = objects
+            .into_iter()
+            .filter(|o| !o.key.starts_with(INTERNAL_PREFIX))
+            .map(|o| (o.key, (o.size, o.etag)))
+            .collect();
+
+        let previous: Option<Snapshot> = self.snapshots.get(&trigger.name).await?;
+        let Some(mut previous) = previous else {
+            return self.snapshots.put(&trigger.name, &current).await;
+        };
+        previous.retain(|key, _| !key.starts_with(INTERNAL_PREFIX));
+
+        // Only submitted events advance the snapshot, the failed ones are repeated on the next poll
+        let mut saved = previous.clone();
+        let mut failed = 0;
+        for event in diff(&trigger.bucket, &previous, &current) {
+            if trigger.matches(&event)
+                && let Err(e) = self.submit(trigger, event.clone()).await
+            {
+                tracing::warn!(
+                    "Failed to submit {:?} of {} for trigger {}: {}",
+                    event.event, event.key, trigger.name, e
+                );
+                failed += 1;
+                continue;
+            }
+            match event.event {
+                EventType::Created => {
+                    if let Some(object) = current.get(&event.key) {
+                        saved.insert(event.key, object.clone());
+                    }
+                }
+                EventType::Deleted => {
+                    saved.remove(&event.key);
+                }
+            }
+        }
+
+        self.snapshots.put(&trigger.name, &saved).await?;
+        if failed > 0 {
+            return Err(AppError::Unavailable(format!(
+                "{} events are retried on the next poll",
+                failed
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Created (new or changed ETag) and deleted objects between two snapshots
+pub fn diff(bucket: &str, previous: &Snapshot, current: &Snapshot) -> Vec<ObjectEvent> {
+    let now = unix_now();
+    let event = |event, key: &str, (size, etag): &(i64, Option<String>)| ObjectEvent {
+        event,
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        size: Some(*size),
+        etag: etag.clone(),
+        source: TriggerSource::Poll,
+        time: now,
+        trigger: String::new(),
+    };
+
+    let mut events: Vec<ObjectEvent> = current
+        .iter()
+        .filter(|(key, object)| previous.get(*key).is_none_or(|p| p.1 != object.1))
+        .map(|(key, object)| event(EventType::Created, key, object))
+        .collect();
+    events.extend(
+        previous
+            .iter()
+            .filter(|(key, _)| !current.contains_key(*key))
+            .map(|(key, object)| event(EventType::Deleted, key, object)),
+    );
+    events
+}
+
+/// S3 bucket notification as sent by AWS, MinIO and RustFS
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Notification {
+    #[serde(default)]
+    pub records: Vec<NotificationRecord>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationRecord {
+    pub event_name: String,
+    pub s3: NotificationEntity,
+}
+
+#[derive(Deserialize)]
+pub struct NotificationEntity {
+    pub bucket: NotificationBucket,
+    pub object: NotificationObject,
+}
+
+#[derive(Deserialize)]
+pub struct NotificationBucket {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationObject {
+    pub key: String,
+    pub size: Option<i64>,
+    pub e_tag: Option<String>,
+}
+
+impl NotificationRecord {
+    /// `None` for event types that don't trigger functions (e.g. restores, replication)
+    pub fn into_event(self) -> Option<ObjectEvent> {
+        let name = self.event_name.trim_start_matches("s3:");
+        let event = if name.starts_with("ObjectCreated") {
+            EventType::Created
+        } else if name.starts_with("ObjectRemoved") {
+            EventType::Deleted
+        } else {
+            return None;
+        };
+
+        // Keys are form encoded in notifications
+        let key = decode_key(&self.s3.object.key.replace('+', " "));
+        if key.starts_with(INTERNAL_PREFIX) {
+            return None;
+        }
+
+        Some(ObjectEvent {
+            event,
+            bucket: self.s3.bucket.name,
+            key,
+            size: self.s3.object.size,
+            etag: self.s3.object.e_tag,
+            source: TriggerSource::Notification,
+            time: unix_now(),
+            trigger: String::new(),
+        })
+    }
+}
@@ -0,0 +1,223 @@
+use crate::error::AppError;
+use crate::tools::s3::{ObjectMeta, PutResult, INTERNAL_PREFIX, S3};
+use crate::tools::{env_or, unix_now};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::CompletedPart;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// S3 requires at least 5 MB for every part except the last one
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Streams chunks into an S3 multipart upload while enforcing the size limit.
+///
+/// Only the part being received is held in memory, the bytes are hashed on the way
+/// and spooled to a temporary file that is read back once for validation. The object
+/// only appears in the bucket once `complete` is called. The multipart upload is
+/// created with the first part, so the metadata can be set until then. Dropping an
+/// unfinished upload aborts it, also when the handler is cancelled by a disconnect.
+pub struct StreamedUpload {
+    s3: Arc<S3>,
+    bucket: String,
+    key: String,
+    meta: ObjectMeta,
+    upload_id: Option<String>,
+    parts: Vec<CompletedPart>,
+    /// Bytes of the next part, less than `PART_SIZE` between writes
+    part: Vec<u8>,
+    size: usize,
+    hasher: Sha256,
+    max_size: usize,
+    spool: Option<(PathBuf, tokio::fs::File)>,
+    finished: bool,
+}
+
+impl StreamedUpload {
+    pub fn new(s3: Arc<S3>, bucket: &str, key: &str, max_size: usize) -> StreamedUpload {
+        StreamedUpload {
+            s3,
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            meta: ObjectMeta::default(),
+            upload_id: None,
+            parts: Vec::new(),
+            part: Vec::new(),
+            size: 0,
+            hasher: Sha256::new(),
+            max_size,
+            spool: None,
+            finished: false,
+        }
+    }
+
+    pub fn set_meta(&mut self, meta: ObjectMeta) {
+        self.meta = meta;
+    }
+
+    pub fn meta(&self) -> &ObjectMeta {
+        &self.meta
+    }
+
+    pub async fn write(&mut self, chunk: &[u8]) -> Result<(), AppError> {
+        if self.size + chunk.len() > self.max_size {
+            return Err(AppError::Validation(format!(
+                "File too large. Maximum size is {}MB",
+                self.max_size / 1024 / 1024
+            )));
+        }
+
+        self.hasher.update(chunk);
+        self.size += chunk.len();
+        self.spool().await?.write_all(chunk).await?;
+        self.part.extend_from_slice(chunk);
+
+        while self.part.len() >= PART_SIZE {
+            let rest = self.part.split_off(PART_SIZE);
+            let body = std::mem::replace(&mut self.part, rest);
+            self.upload_part(body).await?;
+        }
+        Ok(())
+    }
+
+    async fn spool(&mut self) -> Result<&mut tokio::fs::File, AppError> {
+        if self.spool.is_none() {
+            let path = std::env::temp_dir().join(format!("faas-upload-{:032x}.part", rand::random::<u128>()));
+            let file = tokio::fs::File::create(&path).await?;
+            self.spool = Some((path, file));
+        }
+        Ok(self.spool.as_mut().map(|(_, file)| file).expect("spool is created above"))
+    }
+
+    async fn upload_part(&mut self, body: Vec<u8>) -> Result<(), AppError> {
+        let upload_id = match &self.upload_id {
+            Some(upload_id) => upload_id.clone(),
+            None => {
+                let upload_id = self
+                    .s3
+                    .create_multipart_upload(&self.bucket, &self.key, &self.meta)
+                    .await?;
+                self.upload_id = Some(upload_id.clone());
+                upload_id
+            }
+        };
+
+        let part_number = self.parts.len() as i32 + 1;
+        let part = self
+            .s3
+            .upload_part(&self.bucket, &self.key, &upload_id, part_number, ByteStream::from(body))
+            .await?;
+        self.parts.push(part);
+        Ok(())
+    }
+
+    /// Received bytes read back from the spool, bounded by the size limit
+    pub async fn data(&mut self) -> Result<Vec<u8>, AppError> {
+        let Some((path, file)) = &mut self.spool else {
+            return Ok(Vec::new());
+        };
+        file.flush().await?;
+        Ok(tokio::fs::read(path).await?)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size as u64
+    }
+
+    pub fn sha256(&self) -> String {
+        format!("{:x}", self.hasher.clone().finalize())
+    }
+
+    pub async fn complete(&mut self) -> Result<PutResult, AppError> {
+        if !self.part.is_empty() || self.parts.is_empty() {
+            let body = std::mem::take(&mut self.part);
+            self.upload_part(body).await?;
+        }
+
+        self.finished = true;
+        let upload_id = self.upload_id.clone().unwrap_or_default();
+        self.s3
+            .complete_multipart_upload(&self.bucket, &self.key, &upload_id, std::mem::take(&mut self.parts))
+            .await
+    }
+
+    /// Drops the uploaded parts, a no-op once the upload was completed or aborted
+    pub async fn abort(&mut self) -> Result<(), AppError> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        let Some(upload_id) = &self.upload_id else {
+            return Ok(());
+        };
+        self.s3
+            .abort_multipart_upload(&self.bucket, &self.key, upload_id)
+            .await
+    }
+}
+
+impl Drop for StreamedUpload {
+    fn drop(&mut self) {
+        if let Some((path, _)) = &self.spool
+            && let Err(e) = std::fs::remove_file(path)
+        {
+            tracing::warn!("Failed to remove upload spool {}: {}", path.display(), e);
+        }
+
+        if self.finished {
+            return;
+        }
+        let Some(upload_id) = self.upload_id.take() else {
+            return;
+        };
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let (s3, bucket, key) = (self.s3.clone(), self.bucket.clone(), self.key.clone());
+        handle.spawn(async move {
+            if let Err(e) = s3.abort_multipart_upload(&bucket, &key, &upload_id).await {
+                tracing::warn!("Failed to abort upload of {}: {}", key, e);
+            }
+        });
+    }
+}
+
+fn staging_prefix() -> String {
+    format!("{}uploads/", INTERNAL_PREFIX)
+}
+
+/// Staging key of a presigned upload, the object only reaches `key` once finalized
+pub fn staging_key(upload_id: &str, key: &str) -> String {
+    format!("{}{}/{}", staging_prefix(), upload_id, key)
+}
+
+/// Removes staged uploads that were never finalized once they are older than
+/// `FAAS_UPLOAD_STAGING_SECS`
+pub fn start_staging_sweep(s3: Arc<S3>) {
+    let retention = env_or("FAAS_UPLOAD_STAGING_SECS", 24 * 60 * 60);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = sweep_staged(&s3, retention).await {
+                tracing::warn!("Failed to sweep staged uploads: {}", e);
+            }
+        }
+    });
+}
+
+async fn sweep_staged(s3: &S3, retention: u64) -> Result<(), AppError> {
+    let cutoff = unix_now().saturating_sub(retention) as i64;
+    for bucket in s3.list_buckets().await? {
+        for object in s3.list_objects(&bucket.name, &staging_prefix()).await? {
+            if object.last_modified.is_some_and(|modified| modified < cutoff) {
+                s3.purge(&bucket.name, &object.key).await?;
+            }
+        }
+    }
+    Ok(())
+}
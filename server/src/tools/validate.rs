@@ -0,0 +1,112 @@
+use crate::error::AppError;
+use crate::tools::inspect::{self, ComponentDescription};
+use crate::tools::runtime::Runtime;
+use wasmparser::Parser;
+
+/// Imports a function may use unless configured otherwise with `FAAS_ALLOWED_IMPORTS`
+const DEFAULT_ALLOWED_IMPORTS: [&str; 10] = [
+    "local:faas/invoke",
+    "local:faas/logging",
+    "local:faas/outcome",
+    "wasi:cli/",
+    "wasi:config/",
+    "wasi:io/",
+    "wasi:clocks/",
+    "wasi:random/",
+    "wasi:filesystem/",
+    "wasi:http/",
+];
+
+/// Rules every uploaded component has to satisfy before it is stored
+pub struct Policy {
+    pub max_size: usize,
+    /// Prefixes of the interfaces a component is allowed to import
+    pub allowed_imports: Vec<String>,
+}
+
+impl Policy {
+    pub fn from_env() -> Policy {
+        let max_size = std::env::var("FAAS_MAX_UPLOAD_MB")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(50)
+            * 1024
+            * 1024;
+
+        let allowed_imports = match std::env::var("FAAS_ALLOWED_IMPORTS") {
+            Ok(value) => value
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            Err(_) => DEFAULT_ALLOWED_IMPORTS.iter().map(|s| s.to_string()).collect(),
+        };
+
+        Policy {
+            max_size,
+            allowed_imports,
+        }
+    }
+
+    pub fn allows(&self, import: &str) -> bool {
+        self.allowed_imports.iter().any(|prefix| import.starts_with(prefix))
+    }
+}
+
+/// Also checked before a core module is adapted, which only adds to its size
+pub fn check_size(policy: &Policy, wasm_bytes: &[u8]) -> Result<(), AppError> {
+    if wasm_bytes.is_empty() {
+        return Err(AppError::Validation("File is empty".to_string()));
+    }
+    if wasm_bytes.len() > policy.max_size {
+        return Err(AppError::Validation(format!(
+            "File too large. Maximum size is {}MB",
+            policy.max_size / 1024 / 1024
+        )));
+    }
+    Ok(())
+}
+
+pub fn validate(
+    runtime: &Runtime,
+    policy: &Policy,
+    wasm_bytes: &[u8],
+) -> Result<ComponentDescription, AppError> {
+    check_size(policy, wasm_bytes)?;
+    if Parser::is_core_wasm(wasm_bytes) {
+        return Err(AppError::Validation(
+            "Core module without component type information, build it with wit-bindgen or adapt it with wasi_snapshot_preview1.reactor.wasm".to_string(),
+        ));
+    }
+    if !Parser::is_component(wasm_bytes) {
+        return Err(AppError::Validation("Not a WebAssembly component".to_string()));
+    }
+
+    let description = inspect::describe(runtime, wasm_bytes)
+        .map_err(|e| AppError::Validation(format!("Invalid component: {}", e)))?;
+
+    // Stages are stored to be composed, they can't run on their own
+    let stage = description.world.as_deref() == Some(inspect::FAAS_STAGE_WORLD);
+    if !stage && let Some(reason) = &description.incompatibility {
+        return Err(AppError::Validation(format!(
+            "Component does not match the {} world: {}",
+            inspect::FAAS_EXEC_WORLD,
+            reason
+        )));
+    }
+
+    let denied: Vec<&str> = description
+        .imports
+        .iter()
+        .map(|i| i.name.as_str())
+        .filter(|name| !(policy.allows(name) || stage && *name == inspect::NEXT_IMPORT))
+        .collect();
+    if !denied.is_empty() {
+        return Err(AppError::Validation(format!(
+            "Imports not allowed by the capability policy: {}",
+            denied.join(", ")
+        )));
+    }
+
+    Ok(description)
+}
@@ -0,0 +1,311 @@
+use crate::error::AppError;
+use crate::tools::secrets::{Sealed, Secrets};
+use crate::tools::{backoff, env_or, unix_now};
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashSet};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const CALLBACK_HEADER: &str = "x-faas-callback";
+const SECRET_HEADER: &str = "x-faas-callback-secret";
+/// `x-faas-callback-header-{name}` headers are sent along with the callback
+const HEADER_PREFIX: &str = "x-faas-callback-header-";
+
+const REDACTED: &str = "***";
+
+/// Callback URL registered with an asynchronous execution
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Callback {
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Key of the HMAC-SHA256 signature in `x-faas-signature`, sealed with the master key
+    pub secret: Option<Sealed>,
+}
+
+impl Callback {
+    /// Reads `x-faas-callback`, `x-faas-callback-secret` and `x-faas-callback-header-*`
+    pub fn from_headers(headers: &HeaderMap, secrets: &Secrets) -> Result<Option<Callback>, AppError> {
+        let value = |name: &str| -> Result<Option<String>, AppError> {
+            headers
+                .get(name)
+                .map(|v| {
+                    v.to_str()
+                        .map(|v| v.trim().to_string())
+                        .map_err(|_| AppError::BadRequest(format!("{} must be ASCII", name)))
+                })
+                .transpose()
+        };
+
+        let Some(url) = value(CALLBACK_HEADER)? else {
+            return Ok(None);
+        };
+        let parsed =
+            reqwest::Url::parse(&url).map_err(|e| AppError::BadRequest(format!("Invalid callback {}: {}", url, e)))?;
+        if parsed.scheme() != "http" && parsed.scheme() != "https" {
+            return Err(AppError::BadRequest("Callbacks have to be http or https URLs".to_string()));
+        }
+
+        let mut extra = BTreeMap::new();
+        for name in headers.keys() {
+            if let Some(header) = name.as_str().strip_prefix(HEADER_PREFIX)
+                && let Some(value) = value(name.as_str())?
+            {
+                extra.insert(header.to_string(), value);
+            }
+        }
+
+        let secret = value(SECRET_HEADER)?
+            .map(|secret| {
+                secrets.seal(SECRET_HEADER, &secret).map_err(|e| match e {
+                    AppError::Unavailable(_) => AppError::Unavailable(format!(
+                        "{} is stored sealed, signed callbacks need FAAS_MASTER_KEY to be set",
+                        SECRET_HEADER
+                    )),
+                    e => e,
+                })
+            })
+            .transpose()?;
+        Ok(Some(Callback {
+            url,
+            headers: extra,
+            secret,
+        }))
+    }
+
+    /// Copy without the secret and header values, for API responses
+    pub fn redacted(&self) -> Callback {
+        Callback {
+            url: self.url.clone(),
+            headers: self
+                .headers
+                .keys()
+                .map(|k| (k.clone(), REDACTED.to_string()))
+                .collect(),
+            secret: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeliveryAttempt {
+    pub attempt: u32,
+    pub at: u64,
+    /// HTTP status of the response, `None` when no response was received
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+pub enum Outcome {
+    Delivered,
+    Retry,
+    Failed,
+}
+
+/// Sends callbacks with HMAC signatures, retried with exponential backoff by the caller
+pub struct Webhooks {
+    client: reqwest::Client,
+    secrets: Arc<Secrets>,
+    pub attempts: u32,
+    base_delay: Duration,
+    /// Hosts callbacks may go to, any public address when unset
+    allowed_hosts: Option<HashSet<String>>,
+}
+
+impl Webhooks {
+    /// Reads `FAAS_WEBHOOK_ATTEMPTS`, `FAAS_WEBHOOK_TIMEOUT_MS`, `FAAS_WEBHOOK_BACKOFF_MS`
+    /// and `FAAS_CALLBACK_ALLOWED_HOSTS`
+    pub fn from_env(secrets: Arc<Secrets>) -> Result<Webhooks, AppError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(env_or("FAAS_WEBHOOK_TIMEOUT_MS", 10_000)))
+            // A redirect would skip the host check
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let allowed_hosts = std::env::var("FAAS_CALLBACK_ALLOWED_HOSTS").ok().map(|hosts| {
+            hosts
+                .split(',')
+                .map(|host| host.trim().to_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect()
+        });
+
+        Ok(Webhooks {
+            client,
+            secrets,
+            attempts: env_or("FAAS_WEBHOOK_ATTEMPTS", 5).max(1) as u32,
+            base_delay: Duration::from_millis(env_or("FAAS_WEBHOOK_BACKOFF_MS", 1000)),
+            allowed_hosts,
+        })
+    }
+
+    /// Refuses hosts missing from the allow list, without a list hosts resolving to
+    /// loopback, private or link-local addresses
+    pub async fn check(&self, url: &str) -> Result<(), AppError> {
+        let url =
+            reqwest::Url::parse(url).map_err(|e| AppError::BadRequest(format!("Invalid callback {}: {}", url, e)))?;
+        let Some(host) = url.host_str().map(str::to_lowercase) else {
+            return Err(AppError::BadRequest(format!("Callback {} has no host", url)));
+        };
+        if let Some(allowed) = &self.allowed_hosts {
+            if allowed.contains(&host) {
+                return Ok(());
+            }
+            return Err(AppError::Forbidden(format!("Callbacks to {} are not allowed", host)));
+        }
+
+        let addresses: Vec<IpAddr> = match host.trim_start_matches('[').trim_end_matches(']').parse() {
+            Ok(ip) => vec![ip],
+            Err(_) => {
+                let port = url.port_or_known_default().unwrap_or(80);
+                tokio::net::lookup_host((host.as_str(), port))
+                    .await
+                    .map_err(|e| AppError::BadRequest(format!("Failed to resolve callback host {}: {}", host, e)))?
+                    .map(|address| address.ip())
+                    .collect()
+            }
+        };
+        match addresses.iter().find(|ip| !is_public(ip)) {
+            Some(ip) => Err(AppError::Forbidden(format!(
+                "Callback host {} resolves to the internal address {}",
+                host, ip
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Delay before the attempt following `attempt`
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        backoff(self.base_delay, attempt)
+    }
+
+    pub async fn send(
+        &self,
+        callback: &Callback,
+        job_id: &str,
+        body: &[u8],
+        attempt: u32,
+    ) -> (DeliveryAttempt, Outcome) {
+        let timestamp = unix_now();
+        let mut record = DeliveryAttempt {
+            attempt,
+            at: timestamp,
+            status: None,
+            error: None,
+            duration_ms: 0,
+        };
+        // Again on every attempt, the host could resolve to another address by now
+        if let Err(e) = self.check(&callback.url).await {
+            record.error = Some(e.to_string());
+            let outcome = match e {
+                AppError::Forbidden(_) => Outcome::Failed,
+                _ => Outcome::Retry,
+            };
+            return (record, outcome);
+        }
+        let secret = match &callback.secret {
+            Some(sealed) => match self.secrets.open(SECRET_HEADER, sealed) {
+                Ok(secret) => Some(secret),
+                Err(e) => {
+                    record.error = Some(e.to_string());
+                    return (record, Outcome::Failed);
+                }
+            },
+            None => None,
+        };
+
+        let mut request = self
+            .client
+            .post(&callback.url)
+            .header("content-type", "application/json")
+            .header("x-faas-job", job_id)
+            .header("x-faas-delivery", attempt.to_string())
+            .header("x-faas-timestamp", timestamp.to_string());
+        for (name, value) in &callback.headers {
+            request = request.header(name, value);
+        }
+        if let Some(secret) = &secret {
+            request = request.header("x-faas-signature", sign(secret, timestamp, body));
+        }
+
+        let start = Instant::now();
+        let result = request.body(body.to_vec()).send().await;
+        record.duration_ms = start.elapsed().as_millis() as u64;
+
+        let outcome = match result {
+            Ok(response) => {
+                let status = response.status();
+                record.status = Some(status.as_u16());
+                if status.is_success() {
+                    Outcome::Delivered
+                } else {
+                    record.error = Some(format!("Callback answered {}", status));
+                    // Other client errors won't change with a retry
+                    if status.is_server_error() || status.as_u16() == 408 || status.as_u16() == 429 {
+                        Outcome::Retry
+                    } else {
+                        Outcome::Failed
+                    }
+                }
+            }
+            Err(e) => {
+                record.error = Some(e.to_string());
+                Outcome::Retry
+            }
+        };
+
+        (record, outcome)
+    }
+}
+
+/// False for loopback, private, link-local, shared and other non-routable addresses
+pub fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // 100.64.0.0/10, carrier-grade NAT
+                || (a == 100 && b & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(&IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // fc00::/7 unique local and fe80::/10 link-local
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+/// `sha256={hex}` of the HMAC-SHA256 over `{timestamp}.{body}`
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
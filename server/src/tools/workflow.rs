@@ -0,0 +1,716 @@
+use crate::error::AppError;
+use crate::tools::invoke::{Invocation, Invoker};
+use crate::tools::jobs::{system_bucket, validate_id, JobError};
+use crate::tools::s3::S3;
+use crate::tools::store::Store;
+use crate::tools::{alias, backoff, env_or, names, unix_now};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+const MAX_STEPS: usize = 64;
+const MAX_PARALLELISM: usize = 32;
+const MAX_ATTEMPTS: u32 = 10;
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Retry {
+    /// Executions including the first one
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+    /// Delay before the first retry, doubled on every attempt
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+}
+
+impl Default for Retry {
+    fn default() -> Retry {
+        Retry {
+            attempts: default_attempts(),
+            backoff_ms: default_backoff_ms(),
+        }
+    }
+}
+
+fn default_attempts() -> u32 {
+    1
+}
+
+fn default_backoff_ms() -> u64 {
+    1000
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MapOptions {
+    /// Elements executed at the same time
+    #[serde(default = "default_parallelism")]
+    pub parallelism: usize,
+}
+
+fn default_parallelism() -> usize {
+    4
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorkflowStep {
+    pub id: String,
+    pub bucket: String,
+    /// Function key or `key@alias`, `version` wins over the alias
+    pub key: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Steps whose outputs are the input, merged into an object by step id when there are several
+    #[serde(default)]
+    pub needs: Vec<String>,
+    /// Calls the function for every element of the input array
+    #[serde(default)]
+    pub map: Option<MapOptions>,
+    /// Retries on guest errors and timeouts
+    #[serde(default)]
+    pub retry: Option<Retry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Workflow {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub steps: Vec<WorkflowStep>,
+    /// Step whose output is the result, by default the steps no other step needs
+    #[serde(default)]
+    pub output: Option<String>,
+    #[serde(default)]
+    pub created_at: u64,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkflowStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    /// Not executed because the workflow failed or was cancelled
+    Skipped,
+}
+
+/// Elements of a map step, `completed` is only tracked while the step runs
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MapProgress {
+    pub total: usize,
+    pub completed: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StepState {
+    pub status: StepStatus,
+    pub attempts: u32,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub output: Option<serde_json::Value>,
+    pub error: Option<JobError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<MapProgress>,
+    /// Recorded outputs of the finished elements of a running map step, kept across restarts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub elements: Vec<Option<serde_json::Value>>,
+}
+
+impl StepState {
+    fn pending() -> StepState {
+        StepState {
+            status: StepStatus::Pending,
+            attempts: 0,
+            started_at: None,
+            finished_at: None,
+            output: None,
+            error: None,
+            items: None,
+            elements: vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorkflowRun {
+    pub id: String,
+    /// Definition at the start, later changes don't affect the run
+    pub workflow: Workflow,
+    pub status: WorkflowStatus,
+    pub input: serde_json::Value,
+    pub output: Option<serde_json::Value>,
+    pub error: Option<JobError>,
+    /// Step that failed the workflow
+    pub failed_step: Option<String>,
+    pub steps: BTreeMap<String, StepState>,
+    pub retention: u64,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+    /// Outputs as returned, the recorded ones have the secret values replaced.
+    /// Steps that finished before a restart pass on their recorded output
+    #[serde(skip)]
+    outputs: HashMap<String, serde_json::Value>,
+}
+
+impl WorkflowRun {
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.finished_at
+            .is_some_and(|finished| finished + self.retention <= now)
+    }
+
+    /// Steps whose needs have all succeeded, with their input
+    fn ready(&self) -> Vec<(WorkflowStep, serde_json::Value)> {
+        self.workflow
+            .steps
+            .iter()
+            .filter(|step| self.steps.get(&step.id).is_some_and(|s| s.status == StepStatus::Pending))
+            .filter(|step| {
+                step.needs.iter().all(|need| {
+                    self.steps
+                        .get(need)
+                        .is_some_and(|s| s.status == StepStatus::Succeeded)
+                })
+            })
+            .map(|step| (step.clone(), self.input_of(step)))
+            .collect()
+    }
+
+    fn input_of(&self, step: &WorkflowStep) -> serde_json::Value {
+        let output = |id: &String| {
+            self.outputs
+                .get(id)
+                .cloned()
+                .or_else(|| self.steps.get(id).and_then(|s| s.output.clone()))
+                .unwrap_or_default()
+        };
+        match step.needs.as_slice() {
+            [] => self.input.clone(),
+            [need] => output(need),
+            needs => serde_json::Value::Object(needs.iter().map(|n| (n.clone(), output(n))).collect()),
+        }
+    }
+
+    /// Output of the `output` step, or of the steps nothing depends on
+    fn result(&self) -> serde_json::Value {
+        let output = |id: &str| {
+            self.steps
+                .get(id)
+                .and_then(|s| s.output.clone())
+                .unwrap_or_default()
+        };
+        if let Some(id) = &self.workflow.output {
+            return output(id);
+        }
+
+        let needed: HashSet<&str> = self
+            .workflow
+            .steps
+            .iter()
+            .flat_map(|s| s.needs.iter().map(String::as_str))
+            .collect();
+        let sinks: Vec<&str> = self
+            .workflow
+            .steps
+            .iter()
+            .map(|s| s.id.as_str())
+            .filter(|id| !needed.contains(id))
+            .collect();
+        match sinks.as_slice() {
+            [id] => output(id),
+            ids => serde_json::Value::Object(ids.iter().map(|id| (id.to_string(), output(id))).collect()),
+        }
+    }
+
+    fn finish(&mut self, status: WorkflowStatus) {
+        self.status = status;
+        self.finished_at = Some(unix_now());
+        for step in self.steps.values_mut() {
+            if matches!(step.status, StepStatus::Pending | StepStatus::Running) {
+                step.status = StepStatus::Skipped;
+                step.items = None;
+            }
+        }
+        if status == WorkflowStatus::Succeeded {
+            self.output = Some(self.result());
+        }
+    }
+}
+
+/// A workflow executing on this server
+struct ActiveRun {
+    run: Mutex<WorkflowRun>,
+    /// Serializes the writes, the latest state always wins
+    saving: tokio::sync::Mutex<()>,
+    token: CancellationToken,
+}
+
+/// DAG workflows whose independent steps run in parallel on the tokio runtime.
+///
+/// Definitions and runs are persisted in the system bucket, the state is saved after every
+/// step so a restart resumes running workflows with the steps that did not finish.
+pub struct Workflows {
+    invoker: Arc<Invoker>,
+    definitions: Store,
+    runs: Store,
+    retention: u64,
+    active: Mutex<HashMap<String, Arc<ActiveRun>>>,
+}
+
+impl Workflows {
+    /// Reads `FAAS_WORKFLOW_RETENTION_SECS` and resumes the running workflows
+    pub async fn start(s3: Arc<S3>, invoker: Arc<Invoker>) -> Result<Arc<Workflows>, AppError> {
+        s3.ensure_bucket(&system_bucket()).await?;
+
+        let workflows = Arc::new(Workflows {
+            invoker,
+            definitions: Store::new(s3.clone(), "workflows"),
+            runs: Store::new(s3, "workflow-runs"),
+            retention: env_or("FAAS_WORKFLOW_RETENTION_SECS", 7 * 24 * 60 * 60),
+            active: Mutex::new(HashMap::new()),
+        });
+
+        workflows.runs.start_sweep(WorkflowRun::is_expired);
+
+        workflows.recover().await?;
+        Ok(workflows)
+    }
+
+    pub async fn get(&self, name: &str) -> Result<Workflow, AppError> {
+        names::validate_name("workflow", name)?;
+        self.definitions
+            .get(name)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Workflow {} not found", name)))
+    }
+
+    pub async fn put(&self, mut workflow: Workflow) -> Result<Workflow, AppError> {
+        names::validate_name("workflow", &workflow.name)?;
+        validate_graph(&workflow)?;
+        for step in &workflow.steps {
+            let (key, _) = alias::split_alias(&step.key);
+            names::validate_object(&step.bucket, key)?;
+        }
+
+        if let Ok(existing) = self.get(&workflow.name).await {
+            workflow.created_at = existing.created_at;
+        }
+        self.definitions.put(&workflow.name, &workflow).await?;
+        Ok(workflow)
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<(), AppError> {
+        self.get(name).await?;
+        self.definitions.delete(name).await
+    }
+
+    /// Starts a run of the workflow in the background
+    pub async fn run(self: &Arc<Self>, name: &str, input: serde_json::Value) -> Result<WorkflowRun, AppError> {
+        let workflow = self.get(name).await?;
+        let run = WorkflowRun {
+            id: format!("{:032x}", rand::random::<u128>()),
+            steps: workflow
+                .steps
+                .iter()
+                .map(|s| (s.id.clone(), StepState::pending()))
+                .collect(),
+            workflow,
+            status: WorkflowStatus::Running,
+            input,
+            output: None,
+            error: None,
+            failed_step: None,
+            retention: self.retention,
+            created_at: unix_now(),
+            finished_at: None,
+            outputs: HashMap::new(),
+        };
+        self.runs.put(&run.id, &run).await?;
+        self.spawn(run.clone());
+        Ok(run)
+    }
+
+    /// Current state of a run, with the progress of running map steps
+    pub async fn status(&self, id: &str) -> Result<WorkflowRun, AppError> {
+        validate_id(id)?;
+        let active = self.active.lock().unwrap().get(id).cloned();
+        if let Some(active) = active {
+            return Ok(active.run.lock().unwrap().clone());
+        }
+        self.runs
+            .get::<WorkflowRun>(id)
+            .await?
+            .filter(|run| !run.is_expired(unix_now()))
+            .ok_or_else(|| AppError::NotFound(format!("Workflow run {} not found", id)))
+    }
+
+    /// Cancels a running workflow, running steps are aborted
+    pub async fn cancel(&self, id: &str) -> Result<WorkflowRun, AppError> {
+        let run = self.status(id).await?;
+        let active = self.active.lock().unwrap().get(id).cloned();
+        let Some(active) = active.filter(|_| run.status == WorkflowStatus::Running) else {
+            return Err(AppError::Conflict(format!(
+                "Workflow run {} is already {:?}",
+                id, run.status
+            )));
+        };
+
+        active.run.lock().unwrap().finish(WorkflowStatus::Cancelled);
+        active.token.cancel();
+        self.save(&active).await?;
+        Ok(active.run.lock().unwrap().clone())
+    }
+
+    fn spawn(self: &Arc<Self>, run: WorkflowRun) {
+        let id = run.id.clone();
+        let active = Arc::new(ActiveRun {
+            run: Mutex::new(run),
+            saving: tokio::sync::Mutex::new(()),
+            token: CancellationToken::new(),
+        });
+        self.active.lock().unwrap().insert(id.clone(), active.clone());
+
+        let workflows = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = workflows.execute(&active).await {
+                tracing::warn!("Failed to record workflow run {}: {}", id, e);
+            }
+            workflows.active.lock().unwrap().remove(&id);
+        });
+    }
+
+    /// Starts every step whose needs are met until all steps finished or one failed
+    async fn execute(self: &Arc<Self>, active: &Arc<ActiveRun>) -> Result<(), AppError> {
+        let mut tasks = JoinSet::new();
+        loop {
+            let ready = {
+                let mut run = active.run.lock().unwrap();
+                let ready = run.ready();
+                for (step, _) in &ready {
+                    if let Some(state) = run.steps.get_mut(&step.id) {
+                        state.status = StepStatus::Running;
+                        state.started_at = Some(unix_now());
+                    }
+                }
+                ready
+            };
+            if !ready.is_empty() {
+                self.save(active).await?;
+            }
+            for (step, input) in ready {
+                let workflows = self.clone();
+                let active = active.clone();
+                tasks.spawn(async move {
+                    let result = workflows.run_step(&active, &step, input).await;
+                    (step.id, result)
+                });
+            }
+
+            let joined = tokio::select! {
+                _ = active.token.cancelled() => {
+                    // `cancel` already recorded the run
+                    tasks.abort_all();
+                    return Ok(());
+                }
+                joined = tasks.join_next() => joined,
+            };
+
+            let Some(joined) = joined else {
+                // Nothing running and nothing ready, every step finished
+                {
+                    let mut run = active.run.lock().unwrap();
+                    if run.steps.values().any(|s| s.status == StepStatus::Pending) {
+                        run.error = Some(JobError::from(&AppError::Internal(
+                            "Steps are waiting for steps that never ran".to_string(),
+                        )));
+                        run.finish(WorkflowStatus::Failed);
+                    } else {
+                        run.finish(WorkflowStatus::Succeeded);
+                    }
+                }
+                return self.save(active).await;
+            };
+            let (id, result) = joined.unwrap_or_else(|e| {
+                (String::new(), Err(AppError::Internal(format!("Workflow step panicked: {}", e))))
+            });
+
+            let failed = {
+                let mut run = active.run.lock().unwrap();
+                if run.status != WorkflowStatus::Running {
+                    return Ok(());
+                }
+                let error = result.as_ref().err().map(JobError::from);
+                if let Some(state) = run.steps.get_mut(&id) {
+                    state.finished_at = Some(unix_now());
+                    match result {
+                        Ok(invocation) => {
+                            state.elements.clear();
+                            state.status = StepStatus::Succeeded;
+                            state.output = Some(invocation.recorded_output());
+                            run.outputs.insert(id.clone(), invocation.output);
+                        }
+                        Err(_) => {
+                            state.status = StepStatus::Failed;
+                            state.error = error.clone();
+                        }
+                    }
+                }
+                if error.is_some() {
+                    run.error = error;
+                    run.failed_step = Some(id);
+                    run.finish(WorkflowStatus::Failed);
+                }
+                run.status == WorkflowStatus::Failed
+            };
+            self.save(active).await?;
+            if failed {
+                tasks.abort_all();
+                return Ok(());
+            }
+        }
+    }
+
+    async fn run_step(
+        &self,
+        active: &ActiveRun,
+        step: &WorkflowStep,
+        input: serde_json::Value,
+    ) -> Result<Invocation, AppError> {
+        let retry = step.retry.clone().unwrap_or_default();
+        // Outputs of map elements survive a retry and a restart, only the others run again
+        let mut outputs: Vec<Option<Invocation>> = active
+            .run
+            .lock()
+            .unwrap()
+            .steps
+            .get(&step.id)
+            .map(|state| {
+                state
+                    .elements
+                    .iter()
+                    .map(|element| {
+                        element.clone().map(|output| Invocation {
+                            version: None,
+                            output,
+                            secrets: vec![],
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            if let Some(state) = active.run.lock().unwrap().steps.get_mut(&step.id) {
+                state.attempts = attempt;
+            }
+
+            let result = match &step.map {
+                Some(map) => self.map(active, step, &input, &mut outputs, map.parallelism).await,
+                None => self
+                    .invoker
+                    .invoke(&step.bucket, &step.key, step.version.clone(), &input)
+                    .await,
+            };
+            match result {
+                Err(e) if attempt < retry.attempts && is_retryable(&e) => {
+                    tokio::time::sleep(backoff(Duration::from_millis(retry.backoff_ms), attempt)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Calls the function for every element without an output yet, with bounded parallelism.
+    ///
+    /// The elements run in a `JoinSet` owned by this future: when the step is aborted or an
+    /// element fails, dropping it aborts the elements that are still running.
+    async fn map(
+        &self,
+        active: &ActiveRun,
+        step: &WorkflowStep,
+        input: &serde_json::Value,
+        outputs: &mut Vec<Option<Invocation>>,
+        parallelism: usize,
+    ) -> Result<Invocation, AppError> {
+        let serde_json::Value::Array(items) = input else {
+            return Err(AppError::BadRequest(format!(
+                "Map step {} needs an array as input",
+                step.id
+            )));
+        };
+        if outputs.len() != items.len() {
+            *outputs = vec![None; items.len()];
+        }
+        let progress = |completed: usize| {
+            if let Some(state) = active.run.lock().unwrap().steps.get_mut(&step.id) {
+                state.items = Some(MapProgress { total: items.len(), completed });
+            }
+        };
+        let mut completed = outputs.iter().filter(|o| o.is_some()).count();
+        progress(completed);
+
+        let pending: Vec<usize> = (0..items.len()).filter(|&i| outputs[i].is_none()).collect();
+        let mut pending = pending.into_iter();
+        let mut tasks = JoinSet::new();
+        loop {
+            // Tasks of their own so the elements run on all worker threads
+            while tasks.len() < parallelism.max(1)
+                && let Some(index) = pending.next()
+            {
+                let invoker = self.invoker.clone();
+                let step = step.clone();
+                let item = items[index].clone();
+                tasks.spawn(async move {
+                    let result = invoker
+                        .invoke(&step.bucket, &step.key, step.version.clone(), &item)
+                        .await;
+                    (index, result)
+                });
+            }
+
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            let (index, result) =
+                joined.map_err(|e| AppError::Internal(format!("Map element panicked: {}", e)))?;
+            let invocation = result?;
+            if let Some(state) = active.run.lock().unwrap().steps.get_mut(&step.id) {
+                state.elements.resize(items.len(), None);
+                state.elements[index] = Some(invocation.recorded_output());
+            }
+            outputs[index] = Some(invocation);
+            completed += 1;
+            progress(completed);
+            self.save(active).await?;
+        }
+
+        let mut secrets: Vec<String> = outputs.iter().flatten().flat_map(|o| o.secrets.clone()).collect();
+        secrets.sort();
+        secrets.dedup();
+        Ok(Invocation {
+            version: None,
+            output: serde_json::Value::Array(
+                outputs
+                    .iter()
+                    .map(|o| o.as_ref().map(|o| o.output.clone()).unwrap_or_default())
+                    .collect(),
+            ),
+            secrets,
+        })
+    }
+
+    async fn save(&self, active: &ActiveRun) -> Result<(), AppError> {
+        let _guard = active.saving.lock().await;
+        let run = active.run.lock().unwrap().clone();
+        self.runs.put(&run.id, &run).await
+    }
+
+    /// Resumes running workflows, steps that were running start again
+    async fn recover(self: &Arc<Self>) -> Result<(), AppError> {
+        for mut run in self.runs.list::<WorkflowRun>().await? {
+            if run.status != WorkflowStatus::Running {
+                continue;
+            }
+            for state in run.steps.values_mut() {
+                if state.status == StepStatus::Running {
+                    state.status = StepStatus::Pending;
+                    state.items = None;
+                }
+            }
+            tracing::info!("Resuming workflow run {}", run.id);
+            self.spawn(run);
+        }
+        Ok(())
+    }
+}
+
+/// Unique step ids, known needs, no cycles and limits within range
+pub fn validate_graph(workflow: &Workflow) -> Result<(), AppError> {
+    let steps = &workflow.steps;
+    if steps.is_empty() || steps.len() > MAX_STEPS {
+        return Err(AppError::BadRequest(format!("A workflow needs 1 to {} steps", MAX_STEPS)));
+    }
+
+    let mut ids = HashSet::new();
+    for step in steps {
+        names::validate_name("step", &step.id)?;
+        if !ids.insert(step.id.as_str()) {
+            return Err(AppError::BadRequest(format!("Duplicate step {}", step.id)));
+        }
+        if let Some(retry) = &step.retry
+            && !(1..=MAX_ATTEMPTS).contains(&retry.attempts)
+        {
+            return Err(AppError::BadRequest(format!(
+                "Step {} needs 1 to {} attempts",
+                step.id, MAX_ATTEMPTS
+            )));
+        }
+        if let Some(map) = &step.map
+            && !(1..=MAX_PARALLELISM).contains(&map.parallelism)
+        {
+            return Err(AppError::BadRequest(format!(
+                "Step {} needs a parallelism of 1 to {}",
+                step.id, MAX_PARALLELISM
+            )));
+        }
+    }
+    for step in steps {
+        if let Some(need) = step.needs.iter().find(|n| !ids.contains(n.as_str())) {
+            return Err(AppError::BadRequest(format!(
+                "Step {} needs unknown step {}",
+                step.id, need
+            )));
+        }
+    }
+    if let Some(output) = workflow.output.as_ref().filter(|o| !ids.contains(o.as_str())) {
+        return Err(AppError::BadRequest(format!("Unknown output step {}", output)));
+    }
+
+    // Kahn's algorithm, steps left over are part of a cycle
+    let mut remaining: HashMap<&str, usize> =
+        steps.iter().map(|s| (s.id.as_str(), s.needs.len())).collect();
+    let mut ready: Vec<&str> = remaining
+        .iter()
+        .filter(|(_, needs)| **needs == 0)
+        .map(|(id, _)| *id)
+        .collect();
+    while let Some(id) = ready.pop() {
+        remaining.remove(id);
+        for step in steps.iter().filter(|s| s.needs.iter().any(|n| n == id)) {
+            if let Some(needs) = remaining.get_mut(step.id.as_str()) {
+                *needs -= step.needs.iter().filter(|n| *n == id).count();
+                if *needs == 0 {
+                    ready.push(step.id.as_str());
+                }
+            }
+        }
+    }
+    if !remaining.is_empty() {
+        let mut cycle: Vec<&str> = remaining.into_keys().collect();
+        cycle.sort();
+        return Err(AppError::BadRequest(format!(
+            "Steps {} form a cycle",
+            cycle.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Guest traps and timeouts may pass on another attempt, missing functions won't
+fn is_retryable(err: &AppError) -> bool {
+    matches!(err, AppError::WasmtimeError(_) | AppError::Timeout(_))
+}